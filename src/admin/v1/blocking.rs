@@ -0,0 +1,119 @@
+//! A blocking variant of the admin client.
+
+use super::{Members, TransferOwnership};
+use crate::{
+    core::{BlockingRuntime, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+};
+use std::{fmt::Debug, time::Duration};
+use url::Url;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A blocking application administration client.
+///
+/// This wraps the async [`Client`](super::Client), and runs its operations on a
+/// [`BlockingRuntime`]. The operations must not be called from within an async context.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client,
+    runtime: BlockingRuntime,
+}
+
+impl Client {
+    /// Create a new client instance, using its own runtime.
+    pub fn new(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        Self::from_async(super::Client::new(transport, api_url, token_provider))
+    }
+
+    /// Wrap an async client, using its own runtime.
+    pub fn from_async(client: super::Client) -> ClientResult<Self> {
+        Ok(Self::with_runtime(client, BlockingRuntime::new()?))
+    }
+
+    /// Wrap an async client, using an existing runtime.
+    pub fn with_runtime(client: super::Client, runtime: BlockingRuntime) -> Self {
+        Self {
+            inner: client,
+            runtime,
+        }
+    }
+
+    /// The wrapped async client.
+    pub fn inner(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// Set a timeout for requests made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.with_timeout(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Blocking variant of [`super::Client::get_members`].
+    pub fn get_members<A>(&self, application: A) -> ClientResult<Option<Members>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.get_members(application))
+    }
+
+    /// Blocking variant of [`super::Client::update_members`].
+    pub fn update_members<A>(&self, application: A, members: Members) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.update_members(application, members))
+    }
+
+    /// Blocking variant of [`super::Client::initiate_app_transfer`].
+    pub fn initiate_app_transfer<A, U>(&self, application: A, username: U) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+        U: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.initiate_app_transfer(application, username))
+    }
+
+    /// Blocking variant of [`super::Client::cancel_app_transfer`].
+    pub fn cancel_app_transfer<A>(&self, application: A) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.cancel_app_transfer(application))
+    }
+
+    /// Blocking variant of [`super::Client::accept_app_transfer`].
+    pub fn accept_app_transfer<A>(&self, application: A) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.accept_app_transfer(application))
+    }
+
+    /// Blocking variant of [`super::Client::read_app_transfer`].
+    pub fn read_app_transfer<A>(&self, application: A) -> ClientResult<Option<TransferOwnership>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.read_app_transfer(application))
+    }
+}
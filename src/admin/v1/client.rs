@@ -1,17 +1,18 @@
 use super::data::*;
-use crate::core::CoreClient;
+use crate::core::{CoreClient, RequestOptions, Transport};
 use crate::error::ClientError;
 use crate::openid::TokenProvider;
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 use tracing::instrument;
 use url::Url;
 
 /// A client for drogue cloud application administration API, backed by reqwest.
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     api_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
 }
 
 enum AdministrationOperation {
@@ -23,27 +24,51 @@ enum AdministrationOperation {
 type ClientResult<T> = Result<T, ClientError>;
 
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "admin"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new client instance.
     pub fn new(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         api_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             api_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+        }
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
         }
+        client
     }
 
     fn url(&self, application: &str, operation: AdministrationOperation) -> ClientResult<Url> {
@@ -74,8 +99,11 @@ impl Client {
     where
         A: AsRef<str> + Debug,
     {
-        self.read(self.url(application.as_ref(), AdministrationOperation::Members)?)
-            .await
+        self.read(
+            "get_members",
+            self.url(application.as_ref(), AdministrationOperation::Members)?,
+        )
+        .await
     }
 
     /// Update the application members and their roles
@@ -85,6 +113,7 @@ impl Client {
         A: AsRef<str> + Debug,
     {
         self.update(
+            "update_members",
             self.url(application.as_ref(), AdministrationOperation::Members)?,
             Some(members),
         )
@@ -107,6 +136,7 @@ impl Client {
         };
 
         self.update(
+            "initiate_app_transfer",
             self.url(application.as_ref(), AdministrationOperation::Transfer)?,
             Some(payload),
         )
@@ -119,8 +149,11 @@ impl Client {
     where
         A: AsRef<str> + Debug,
     {
-        self.delete(self.url(application.as_ref(), AdministrationOperation::Transfer)?)
-            .await
+        self.delete(
+            "cancel_app_transfer",
+            self.url(application.as_ref(), AdministrationOperation::Transfer)?,
+        )
+        .await
     }
 
     /// Accept the application ownership transfer
@@ -130,6 +163,7 @@ impl Client {
         A: AsRef<str> + Debug,
     {
         self.update(
+            "accept_app_transfer",
             self.url(application.as_ref(), AdministrationOperation::Accept)?,
             None::<()>,
         )
@@ -145,7 +179,10 @@ impl Client {
     where
         A: AsRef<str> + Debug,
     {
-        self.read(self.url(application.as_ref(), AdministrationOperation::Transfer)?)
-            .await
+        self.read(
+            "read_app_transfer",
+            self.url(application.as_ref(), AdministrationOperation::Transfer)?,
+        )
+        .await
     }
 }
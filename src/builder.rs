@@ -0,0 +1,282 @@
+use crate::{discovery::v1::Endpoints, error::ClientError, openid::TokenProvider, DrogueClient};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, Proxy,
+};
+use std::{path::PathBuf, time::Duration};
+use tracing::instrument;
+use url::Url;
+
+/// The default user agent, sent by clients created by the [`ClientBuilder`].
+pub const USER_AGENT: &str = concat!("drogue-client/", env!("CARGO_PKG_VERSION"));
+
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+#[derive(Clone, Debug)]
+enum CaCertificates {
+    Pem(Vec<u8>),
+    File(PathBuf),
+}
+
+/// A builder for the HTTP transport, shared by all service clients.
+///
+/// The builder creates a [`reqwest::Client`], which can be passed to any of the service clients,
+/// or used to create a [`DrogueClient`] directly.
+///
+/// ```rust,no_run
+/// use drogue_client::{openid::NoTokenProvider, ClientBuilder};
+/// use std::time::Duration;
+/// use url::Url;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let client = ClientBuilder::new()
+///     .add_ca_certificates_file("/etc/drogue/ca.crt")
+///     .timeout(Duration::from_secs(10))
+///     .discover(Url::parse("https://api.sandbox.drogue.cloud")?, NoTokenProvider)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    ca_certificates: Vec<CaCertificates>,
+    insecure: bool,
+    proxy: Option<String>,
+    user_agent: String,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            ca_certificates: Vec::new(),
+            insecure: false,
+            proxy: None,
+            user_agent: USER_AGENT.to_string(),
+            headers: HeaderMap::new(),
+            timeout: None,
+            connect_timeout: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Create a new builder, using the default settings.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Trust the CA certificates of a PEM encoded bundle, in addition to the system ones.
+    ///
+    /// This is required for clusters using locally generated certificates, which is reported
+    /// by [`Endpoints::local_certs`].
+    pub fn add_ca_certificates_pem<P>(mut self, pem: P) -> Self
+    where
+        P: Into<Vec<u8>>,
+    {
+        self.ca_certificates.push(CaCertificates::Pem(pem.into()));
+        self
+    }
+
+    /// Trust the CA certificates of a PEM encoded bundle file, in addition to the system ones.
+    ///
+    /// The file is read when the client is being built.
+    pub fn add_ca_certificates_file<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.ca_certificates.push(CaCertificates::File(path.into()));
+        self
+    }
+
+    /// Disable the validation of TLS certificates.
+    ///
+    /// This is intended for development clusters only, never use this in production.
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
+    /// Send all requests through a proxy.
+    pub fn proxy<P>(mut self, url: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Set the user agent, defaults to [`USER_AGENT`].
+    pub fn user_agent<U>(mut self, user_agent: U) -> Self
+    where
+        U: Into<String>,
+    {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Add a header, sent with every request.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set the timeout for requests, from connecting until the response was received.
+    ///
+    /// If the timeout expires, [`ClientError::Timeout`] is returned.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Build the transport, which can be used with any of the service clients.
+    pub fn build_transport(&self) -> Result<reqwest::Client, ClientError> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .default_headers(self.headers.clone());
+
+        for ca in &self.ca_certificates {
+            for cert in ca.load()? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if self.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Build a client, discovering the service endpoints from the API.
+    #[instrument(skip(token_provider))]
+    pub async fn discover(
+        &self,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> Result<DrogueClient, ClientError> {
+        let client =
+            DrogueClient::discover(self.build_transport()?, api_url, token_provider).await?;
+        self.check_local_certs(client.endpoints());
+        Ok(client)
+    }
+
+    /// Build a client, using already known service endpoints.
+    pub fn with_endpoints(
+        &self,
+        api_url: Url,
+        endpoints: Endpoints,
+        token_provider: impl TokenProvider + 'static,
+    ) -> Result<DrogueClient, ClientError> {
+        self.check_local_certs(&endpoints);
+        Ok(DrogueClient::with_endpoints(
+            self.build_transport()?,
+            api_url,
+            endpoints,
+            token_provider,
+        ))
+    }
+
+    fn check_local_certs(&self, endpoints: &Endpoints) {
+        if endpoints.local_certs && self.ca_certificates.is_empty() && !self.insecure {
+            log::warn!(
+                "The cluster uses local certificates, but no CA certificates are configured"
+            );
+        }
+    }
+}
+
+impl CaCertificates {
+    fn load(&self) -> Result<Vec<Certificate>, ClientError> {
+        let pem = match self {
+            Self::Pem(pem) => pem.clone(),
+            Self::File(path) => std::fs::read(path).map_err(|err| {
+                ClientError::Request(format!(
+                    "Failed to read CA certificates from {}: {}",
+                    path.display(),
+                    err
+                ))
+            })?,
+        };
+
+        let certs = split_pem_bundle(&String::from_utf8_lossy(&pem))
+            .map(|pem| Certificate::from_pem(pem.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if certs.is_empty() {
+            return Err(ClientError::Request(
+                "No CA certificates found in PEM bundle".to_string(),
+            ));
+        }
+
+        Ok(certs)
+    }
+}
+
+/// Split a PEM bundle into the individual certificates.
+fn split_pem_bundle(bundle: &str) -> impl Iterator<Item = &str> {
+    bundle
+        .split_inclusive(PEM_END)
+        .filter(|pem| pem.trim_end().ends_with(PEM_END))
+        .map(|pem| pem.trim())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_pem_bundle() {
+        let bundle = r#"# first
+-----BEGIN CERTIFICATE-----
+Zm9v
+-----END CERTIFICATE-----
+-----BEGIN CERTIFICATE-----
+YmFy
+-----END CERTIFICATE-----
+"#;
+
+        assert_eq!(
+            split_pem_bundle(bundle).collect::<Vec<_>>(),
+            vec![
+                "# first\n-----BEGIN CERTIFICATE-----\nZm9v\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nYmFy\n-----END CERTIFICATE-----"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_transport() {
+        let transport = ClientBuilder::new()
+            .insecure(true)
+            .proxy("http://localhost:3128")
+            .timeout(Duration::from_secs(1))
+            .build_transport();
+        assert!(transport.is_ok());
+
+        let transport = ClientBuilder::new()
+            .add_ca_certificates_pem("foo")
+            .build_transport();
+        assert!(matches!(transport, Err(ClientError::Request(_))));
+    }
+}
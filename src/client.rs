@@ -0,0 +1,390 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::{RetryPolicy, RetryTransport};
+use crate::{
+    admin, command,
+    core::Transport,
+    discovery::{self, v1::Endpoints},
+    error::ClientError,
+    openid::TokenProvider,
+    registry, tokens, user,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::instrument;
+use url::Url;
+
+/// A client for all Drogue Cloud APIs.
+///
+/// The client resolves the URLs of the different services from the discovery endpoint, and hands
+/// out the service clients. All service clients share the same transport, and so the same
+/// connection pool, as well as the same token provider.
+///
+/// Failed requests are retried according to the default [`RetryPolicy`], which only retries
+/// safe and idempotent requests. This can be changed using [`DrogueClient::with_retry_policy`].
+///
+/// ```rust,no_run
+/// use drogue_client::{openid::AccessTokenProvider, DrogueClient};
+/// use url::Url;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let client = DrogueClient::discover(
+///     reqwest::Client::new(),
+///     Url::parse("https://api.sandbox.drogue.cloud")?,
+///     AccessTokenProvider {
+///         user: "foo".into(),
+///         token: "drg_...".into(),
+///     },
+/// )
+/// .await?;
+///
+/// let apps = client.registry()?.list_apps(None).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DrogueClient {
+    transport: Arc<dyn Transport>,
+    token_provider: Arc<dyn TokenProvider>,
+    api_url: Url,
+    endpoints: Endpoints,
+    timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    retry: RetryPolicy,
+}
+
+type ClientResult<T> = Result<T, ClientError>;
+
+impl DrogueClient {
+    /// Create a new client, discovering the service endpoints from the API.
+    #[instrument(skip(transport, token_provider))]
+    pub async fn discover(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        let token_provider: Arc<dyn TokenProvider> = Arc::new(token_provider);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let discovery_transport = RetryTransport::new(transport.clone(), RetryPolicy::default());
+        #[cfg(target_arch = "wasm32")]
+        let discovery_transport = transport.clone();
+
+        let endpoints = discovery::v1::Client::new_authenticated(
+            discovery_transport,
+            api_url.clone(),
+            token_provider.clone(),
+        )
+        .get_authenticated_endpoints()
+        .await?
+        .ok_or_else(|| {
+            ClientError::UnexpectedResponse("Missing endpoint information".to_string())
+        })?;
+
+        Ok(Self::with_endpoints(
+            transport,
+            api_url,
+            endpoints,
+            token_provider,
+        ))
+    }
+
+    /// Create a new client, using already known service endpoints.
+    pub fn with_endpoints(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        endpoints: Endpoints,
+        token_provider: impl TokenProvider + 'static,
+    ) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            token_provider: Arc::new(token_provider),
+            api_url,
+            endpoints,
+            timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            retry: Default::default(),
+        }
+    }
+
+    /// Set a timeout for requests, used by all service clients created by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the policy for retrying failed requests, used by all service clients created by this
+    /// client.
+    ///
+    /// Use [`RetryPolicy::never`] to disable retries.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// The endpoints, used by this client.
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    /// The URL of the API service.
+    ///
+    /// This is the URL announced by the endpoint information, falling back to the URL which was
+    /// used to create the client.
+    pub fn api_url(&self) -> ClientResult<Url> {
+        match &self.endpoints.api {
+            Some(api) => Ok(Url::parse(api)?),
+            None => Ok(self.api_url.clone()),
+        }
+    }
+
+    /// A client for the device registry.
+    pub fn registry(&self) -> ClientResult<registry::v1::Client> {
+        let url = match &self.endpoints.registry {
+            Some(registry) => Url::parse(&registry.url)?,
+            None => self.api_url()?,
+        };
+
+        Ok(self.configure(
+            registry::v1::Client::new(self.transport(), url, self.token_provider.clone()),
+            registry::v1::Client::with_timeout,
+        ))
+    }
+
+    /// A client for the command and control API.
+    pub fn command(&self) -> ClientResult<command::v1::Client> {
+        let url = match &self.endpoints.command_url {
+            Some(command) => Url::parse(command)?,
+            None => self.api_url()?,
+        };
+
+        Ok(self.configure(
+            command::v1::Client::new(self.transport(), url, self.token_provider.clone()),
+            command::v1::Client::with_timeout,
+        ))
+    }
+
+    /// A client for the application administration API.
+    pub fn admin(&self) -> ClientResult<admin::v1::Client> {
+        Ok(self.configure(
+            admin::v1::Client::new(
+                self.transport(),
+                self.api_url()?,
+                self.token_provider.clone(),
+            ),
+            admin::v1::Client::with_timeout,
+        ))
+    }
+
+    /// A client for the access token API.
+    pub fn tokens(&self) -> ClientResult<tokens::v1::Client> {
+        Ok(self.configure(
+            tokens::v1::Client::new(
+                self.transport(),
+                self.api_url()?,
+                self.token_provider.clone(),
+            ),
+            tokens::v1::Client::with_timeout,
+        ))
+    }
+
+    /// A client for the discovery API.
+    pub fn discovery(&self) -> ClientResult<discovery::v1::Client> {
+        Ok(self.configure(
+            discovery::v1::Client::new_authenticated(
+                self.transport(),
+                self.api_url()?,
+                self.token_provider.clone(),
+            ),
+            discovery::v1::Client::with_timeout,
+        ))
+    }
+
+    /// The transport for service clients.
+    fn transport(&self) -> Arc<dyn Transport> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.retry.max_attempts > 1 {
+            return Arc::new(RetryTransport::new(
+                self.transport.clone(),
+                self.retry.clone(),
+            ));
+        }
+
+        self.transport.clone()
+    }
+
+    /// A client for authenticating and authorizing users.
+    ///
+    /// The user service is not announced by the endpoint information, it is part of the API
+    /// service.
+    pub fn user(&self) -> ClientResult<user::v1::Client> {
+        let url = |operation: &str| -> ClientResult<Url> {
+            let mut url = self.api_url()?;
+            url.path_segments_mut()
+                .map_err(|_| ClientError::Request("Failed to get paths".into()))?
+                .pop_if_empty()
+                .extend(&["api", "user", "v1alpha1", operation]);
+            Ok(url)
+        };
+
+        Ok(self.configure(
+            user::v1::Client::new(
+                self.transport(),
+                url("authn")?,
+                url("authz")?,
+                self.token_provider.clone(),
+            ),
+            user::v1::Client::with_timeout,
+        ))
+    }
+
+    /// Apply the settings of this client to a service client.
+    fn configure<C>(&self, client: C, with_timeout: fn(C, Duration) -> C) -> C {
+        match self.timeout {
+            Some(timeout) => with_timeout(client, timeout),
+            None => client,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        core::{TransportRequest, TransportResponse},
+        discovery::v1::RegistryEndpoint,
+        openid::NoTokenProvider,
+        user::v1::authn::{AuthenticationRequest, AuthenticationResponse, Outcome},
+    };
+    use async_trait::async_trait;
+    use reqwest::{header::HeaderMap, StatusCode};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    struct FakeTransport {
+        requests: Mutex<Vec<Url>>,
+    }
+
+    #[async_trait]
+    impl Transport for FakeTransport {
+        async fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> Result<TransportResponse, ClientError> {
+            self.requests.lock().unwrap().push(request.url.clone());
+
+            let body = match request.url.path() {
+                "/api/console/v1alpha1/info" => serde_json::to_vec(&Endpoints {
+                    api: Some("https://api.example.com".into()),
+                    registry: Some(RegistryEndpoint {
+                        url: "https://registry.example.com".into(),
+                    }),
+                    ..Default::default()
+                })?,
+                "/api/user/v1alpha1/authn" => serde_json::to_vec(&AuthenticationResponse {
+                    outcome: Outcome::Unknown,
+                })?,
+                _ => b"[]".to_vec(),
+            };
+
+            Ok(TransportResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover() -> anyhow::Result<()> {
+        let transport = Arc::new(FakeTransport::default());
+        let client = DrogueClient::discover(
+            transport.clone(),
+            Url::parse("http://localhost")?,
+            NoTokenProvider,
+        )
+        .await?;
+
+        assert_eq!(client.api_url()?.as_str(), "https://api.example.com/");
+        assert_eq!(
+            client.endpoints().registry.as_ref().unwrap().url,
+            "https://registry.example.com"
+        );
+
+        client.registry()?.list_apps(None).await?;
+        client.tokens()?.get_tokens().await?;
+        client
+            .user()?
+            .authenticate_access_token(AuthenticationRequest {
+                user_id: "foo".into(),
+                access_token: "bar".into(),
+            })
+            .await?;
+
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(
+            requests.iter().map(|url| url.as_str()).collect::<Vec<_>>(),
+            vec![
+                "http://localhost/api/console/v1alpha1/info",
+                "https://registry.example.com/api/registry/v1alpha1/apps",
+                "https://api.example.com/api/tokens/v1alpha1",
+                "https://api.example.com/api/user/v1alpha1/authn",
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// A transport, failing the first request with `503 Service Unavailable`.
+    #[derive(Debug, Default)]
+    struct UnavailableOnce {
+        calls: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl Transport for UnavailableOnce {
+        async fn execute(&self, _: TransportRequest) -> Result<TransportResponse, ClientError> {
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+
+            Ok(TransportResponse {
+                status: match *calls {
+                    1 => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::OK,
+                },
+                headers: HeaderMap::new(),
+                body: b"[]".to_vec(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> anyhow::Result<()> {
+        let client = |transport| {
+            DrogueClient::with_endpoints(
+                transport,
+                Url::parse("http://localhost").unwrap(),
+                Default::default(),
+                NoTokenProvider,
+            )
+        };
+
+        let transport = Arc::new(UnavailableOnce::default());
+        client(transport.clone())
+            .registry()?
+            .list_apps(None)
+            .await?;
+        assert_eq!(*transport.calls.lock().unwrap(), 2);
+
+        let transport = Arc::new(UnavailableOnce::default());
+        let result = client(transport.clone())
+            .with_retry_policy(RetryPolicy::never())
+            .registry()?
+            .list_apps(None)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(*transport.calls.lock().unwrap(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! A blocking variant of the command client.
+
+use crate::{
+    core::{BlockingRuntime, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+};
+use serde::Serialize;
+use std::{fmt::Debug, time::Duration};
+use url::Url;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A blocking command client.
+///
+/// This wraps the async [`Client`](super::Client), and runs its operations on a
+/// [`BlockingRuntime`]. The operations must not be called from within an async context.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client,
+    runtime: BlockingRuntime,
+}
+
+impl Client {
+    /// Create a new client instance, using its own runtime.
+    pub fn new(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        Self::from_async(super::Client::new(transport, api_url, token_provider))
+    }
+
+    /// Wrap an async client, using its own runtime.
+    pub fn from_async(client: super::Client) -> ClientResult<Self> {
+        Ok(Self::with_runtime(client, BlockingRuntime::new()?))
+    }
+
+    /// Wrap an async client, using an existing runtime.
+    pub fn with_runtime(client: super::Client, runtime: BlockingRuntime) -> Self {
+        Self {
+            inner: client,
+            runtime,
+        }
+    }
+
+    /// The wrapped async client.
+    pub fn inner(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// Set a timeout for requests made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.with_timeout(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Blocking variant of [`super::Client::publish_command`].
+    pub fn publish_command<A, D, C, P>(
+        &self,
+        application: A,
+        device: D,
+        command: C,
+        payload: Option<P>,
+    ) -> ClientResult<Option<()>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+        C: AsRef<str> + Debug,
+        P: Serialize + Send + Sync,
+    {
+        self.runtime.block_on(
+            self.inner
+                .publish_command(application, device, command, payload),
+        )
+    }
+}
@@ -1,43 +1,68 @@
-use crate::core::CoreClient;
+use crate::core::{CoreClient, RequestOptions, Transport};
 use crate::error::ClientError;
 use crate::openid::TokenProvider;
 use serde::Serialize;
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 use tracing::instrument;
 use url::Url;
 
 /// A client for drogue cloud command and control API, backed by reqwest.
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     api_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
 }
 
 type ClientResult<T> = Result<T, ClientError>;
 
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "command"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new client instance.
     pub fn new(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         api_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             api_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+        }
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
         }
+        client
     }
 
     fn url(&self, application: &str, device: &str) -> ClientResult<Url> {
@@ -83,7 +108,7 @@ impl Client {
         let url = self.url(application.as_ref(), device.as_ref())?;
         let query = vec![("command".to_string(), command.as_ref().to_string())];
 
-        self.create_with_query_parameters(url, payload, Some(query))
+        self.create_with_query_parameters("publish_command", url, payload, Some(query))
             .await
     }
 }
@@ -0,0 +1,686 @@
+//! Client configuration, from `drg` context files and the environment.
+//!
+//! The configuration is loaded from a context file, as written by the `drg` command line tool,
+//! and can be overridden using environment variables:
+//!
+//! | Variable | Description |
+//! | -------- | ----------- |
+//! | `DROGUE_CONFIG` | The path to the context file, falls back to `DRGCFG`, as used by `drg` |
+//! | `DROGUE_CONTEXT` | The name of the context to use, instead of the active one |
+//! | `DROGUE_API_URL` | The URL of the API |
+//! | `DROGUE_REGISTRY_URL` | The URL of the device registry |
+//! | `DROGUE_SSO_URL` | The URL of the single sign-on service |
+//! | `DROGUE_APP` | The default application |
+//! | `DROGUE_USER` | The user name, for using an access token |
+//! | `DROGUE_ACCESS_TOKEN` | The access token, requires `DROGUE_USER` |
+//! | `DROGUE_TOKEN` | An OAuth2 bearer token |
+//!
+//! If no path is configured, the context file is searched at `drg_config.yaml` in
+//! `$XDG_CONFIG_HOME`, or `$HOME/.config`.
+//!
+//! OAuth2 bearer tokens, as stored by `drg login`, are refreshed using the token endpoint of the
+//! context when they expire.
+
+use crate::{
+    core::{Transport, TransportRequest},
+    discovery::v1::{Endpoints, RegistryEndpoint},
+    error::ClientError,
+    openid::{AccessTokenProvider, Credentials, Expires, TokenProvider},
+    DrogueClient,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Method,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+/// The file name of the context file, in the configuration directory.
+pub const CONFIG_FILE_NAME: &str = "drg_config.yaml";
+
+/// The OAuth2 client ID, used by `drg` for the single sign-on service.
+pub const DRG_CLIENT_ID: &str = "drogue";
+
+/// An error loading the configuration.
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    /// Failed to read the context file.
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Failed to parse the context file.
+    #[error("invalid context file: {0}")]
+    Syntax(#[from] serde_yaml::Error),
+    /// The requested context does not exist.
+    #[error("unknown context: {0}")]
+    UnknownContext(String),
+    /// The configuration is missing the API URL.
+    #[error("missing API URL, set a context or DROGUE_API_URL")]
+    MissingApiUrl,
+    /// An environment variable contained an invalid URL.
+    #[error("invalid URL in {0}: {1}")]
+    InvalidUrl(&'static str, #[source] url::ParseError),
+}
+
+/// A `drg` style context file, containing multiple named contexts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    /// The name of the active context.
+    #[serde(default)]
+    pub active_context: String,
+    /// The available contexts.
+    #[serde(default)]
+    pub contexts: Vec<Context>,
+}
+
+/// A named context, pointing to a Drogue Cloud instance.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Context {
+    pub name: String,
+    pub drogue_cloud_url: Url,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<Token>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_url: Option<Url>,
+}
+
+/// A token, stored in a context.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Token {
+    /// A Drogue Cloud access token.
+    AccessToken { id: String, token: String },
+    /// An OAuth2 token, as received from the single sign-on service.
+    ///
+    /// The token is refreshed when it expires, if a refresh token and the token URL are known.
+    Bearer {
+        access_token: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        refresh_token: Option<String>,
+    },
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AccessToken { id, .. } => f
+                .debug_struct("AccessToken")
+                .field("id", id)
+                .field("token", &"***")
+                .finish(),
+            Self::Bearer { .. } => f
+                .debug_struct("Bearer")
+                .field("access_token", &"***")
+                .finish(),
+        }
+    }
+}
+
+impl Config {
+    /// Parse a context file from YAML.
+    pub fn from_yaml(yaml: &str) -> Result<Self, ConfigError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Read a context file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let yaml = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.into(),
+            source,
+        })?;
+        Self::from_yaml(&yaml)
+    }
+
+    /// The path of the context file, as configured by the environment.
+    pub fn default_path() -> Option<PathBuf> {
+        default_path(|name| std::env::var(name).ok())
+    }
+
+    /// Get a context by name.
+    pub fn context(&self, name: &str) -> Option<&Context> {
+        self.contexts.iter().find(|context| context.name == name)
+    }
+
+    /// Get the active context.
+    pub fn active_context(&self) -> Option<&Context> {
+        self.context(&self.active_context)
+    }
+}
+
+/// The resolved configuration of a client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientConfig {
+    pub api_url: Url,
+    pub registry_url: Option<Url>,
+    pub sso_url: Option<Url>,
+    pub token_url: Option<Url>,
+    pub default_app: Option<String>,
+    pub token: Option<Token>,
+}
+
+impl From<&Context> for ClientConfig {
+    fn from(context: &Context) -> Self {
+        Self {
+            api_url: context.drogue_cloud_url.clone(),
+            registry_url: context.registry_url.clone(),
+            sso_url: context.auth_url.clone(),
+            token_url: context.token_url.clone(),
+            default_app: context.default_app.clone(),
+            token: context.token.clone(),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Load the configuration from the context file and the environment.
+    ///
+    /// A missing context file is not an error, as long as the environment provides the
+    /// API URL.
+    pub fn load() -> Result<Self, ConfigError> {
+        let env = |name: &str| std::env::var(name).ok();
+
+        let config = match default_path(env) {
+            Some(path) if path.exists() => Config::from_file(path)?,
+            _ => Config::default(),
+        };
+
+        Self::from_config(&config, env)
+    }
+
+    /// Load the configuration from the environment only.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_config(&Config::default(), |name| std::env::var(name).ok())
+    }
+
+    fn from_config<F>(config: &Config, env: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let context = match env("DROGUE_CONTEXT") {
+            Some(name) => Some(
+                config
+                    .context(&name)
+                    .ok_or(ConfigError::UnknownContext(name))?,
+            ),
+            None => config.active_context(),
+        };
+
+        let url = |name: &'static str| {
+            env(name)
+                .map(|url| Url::parse(&url).map_err(|err| ConfigError::InvalidUrl(name, err)))
+                .transpose()
+        };
+
+        let api_url = url("DROGUE_API_URL")?;
+        let mut result = match (context, api_url) {
+            (Some(context), api_url) => {
+                let mut result = Self::from(context);
+                if let Some(api_url) = api_url {
+                    result.api_url = api_url;
+                }
+                result
+            }
+            (None, Some(api_url)) => Self {
+                api_url,
+                registry_url: None,
+                sso_url: None,
+                token_url: None,
+                default_app: None,
+                token: None,
+            },
+            (None, None) => return Err(ConfigError::MissingApiUrl),
+        };
+
+        if let Some(registry_url) = url("DROGUE_REGISTRY_URL")? {
+            result.registry_url = Some(registry_url);
+        }
+        if let Some(sso_url) = url("DROGUE_SSO_URL")? {
+            result.sso_url = Some(sso_url);
+        }
+        if let Some(app) = env("DROGUE_APP") {
+            result.default_app = Some(app);
+        }
+
+        if let (Some(id), Some(token)) = (env("DROGUE_USER"), env("DROGUE_ACCESS_TOKEN")) {
+            result.token = Some(Token::AccessToken { id, token });
+        } else if let Some(access_token) = env("DROGUE_TOKEN") {
+            result.token = Some(Token::Bearer {
+                access_token,
+                refresh_token: None,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Create a token provider for the configured token.
+    ///
+    /// Bearer tokens are refreshed using a default [`reqwest::Client`].
+    pub fn token_provider(&self) -> ConfigTokenProvider {
+        self.token_provider_with(Arc::new(reqwest::Client::new()))
+    }
+
+    /// Create a token provider for the configured token, refreshing bearer tokens using the
+    /// provided transport.
+    pub fn token_provider_with(&self, transport: Arc<dyn Transport>) -> ConfigTokenProvider {
+        ConfigTokenProvider {
+            token: Arc::new(Mutex::new(self.token.clone())),
+            token_url: self.token_url.clone(),
+            transport,
+        }
+    }
+
+    /// The endpoints, as known from the configuration.
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints {
+            api: Some(self.api_url.to_string()),
+            registry: self.registry_url.as_ref().map(|url| RegistryEndpoint {
+                url: url.to_string(),
+            }),
+            sso: self.sso_url.as_ref().map(|url| url.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Create a client, using the endpoints known from the configuration.
+    pub fn client(&self, transport: impl Transport + 'static) -> DrogueClient {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        DrogueClient::with_endpoints(
+            transport.clone(),
+            self.api_url.clone(),
+            self.endpoints(),
+            self.token_provider_with(transport),
+        )
+    }
+
+    /// Create a client, discovering the endpoints from the API.
+    pub async fn discover(
+        &self,
+        transport: impl Transport + 'static,
+    ) -> Result<DrogueClient, ClientError> {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        DrogueClient::discover(
+            transport.clone(),
+            self.api_url.clone(),
+            self.token_provider_with(transport),
+        )
+        .await
+    }
+}
+
+/// A token provider, using the token of a [`ClientConfig`].
+///
+/// A bearer token, which expires soon, is refreshed using the refresh token and the token URL
+/// of the configuration. If that is not possible, an error is returned, instead of using the
+/// expired token.
+#[derive(Clone, Debug)]
+pub struct ConfigTokenProvider {
+    token: Arc<Mutex<Option<Token>>>,
+    token_url: Option<Url>,
+    transport: Arc<dyn Transport>,
+}
+
+/// The response of the token endpoint, to a refresh request.
+#[derive(Deserialize)]
+struct RefreshResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+impl ConfigTokenProvider {
+    /// Get a valid bearer token, refreshing it if necessary.
+    async fn bearer(
+        &self,
+        access_token: String,
+        refresh_token: Option<String>,
+    ) -> Result<String, ClientError> {
+        match jwt_expires(&access_token) {
+            Some(expires) if expires.expires_before(chrono::Duration::seconds(30)) => {}
+            // not expired, or we can't tell
+            _ => return Ok(access_token),
+        }
+
+        let (refresh_token, token_url) = match (refresh_token, &self.token_url) {
+            (Some(refresh_token), Some(token_url)) => (refresh_token, token_url.clone()),
+            _ => {
+                return Err(ClientError::Token(
+                    "The bearer token expired and can't be refreshed, log in again".into(),
+                ))
+            }
+        };
+
+        log::debug!("Refreshing bearer token using {}", token_url);
+
+        let form = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("grant_type", "refresh_token")
+            .append_pair("refresh_token", &refresh_token)
+            .append_pair("client_id", DRG_CLIENT_ID)
+            .finish();
+        let request = TransportRequest::new(Method::POST, token_url)
+            .header(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            )
+            .body(form.into_bytes());
+
+        let response = self.transport.execute(request).await?;
+        if !response.status.is_success() {
+            return Err(ClientError::Token(
+                format!(
+                    "Failed to refresh the bearer token: HTTP {}",
+                    response.status
+                )
+                .into(),
+            ));
+        }
+        let response: RefreshResponse = response.json()?;
+
+        *self.token.lock().unwrap() = Some(Token::Bearer {
+            access_token: response.access_token.clone(),
+            refresh_token: response.refresh_token.or(Some(refresh_token)),
+        });
+
+        Ok(response.access_token)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl TokenProvider for ConfigTokenProvider {
+    async fn provide_access_token(&self) -> Result<Option<Credentials>, ClientError> {
+        let token = self.token.lock().unwrap().clone();
+        match token {
+            Some(Token::AccessToken { id, token }) => {
+                AccessTokenProvider { user: id, token }
+                    .provide_access_token()
+                    .await
+            }
+            Some(Token::Bearer {
+                access_token,
+                refresh_token,
+            }) => Ok(Some(Credentials::Bearer(
+                self.bearer(access_token, refresh_token).await?,
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Get the expiration of a JWT, without validating it.
+fn jwt_expires(token: &str) -> Option<DateTime<Utc>> {
+    #[derive(Deserialize)]
+    struct Claims {
+        exp: i64,
+    }
+
+    let claims = token.split('.').nth(1)?;
+    let claims = base64::decode_config(claims, base64::URL_SAFE_NO_PAD).ok()?;
+    let claims: Claims = serde_json::from_slice(&claims).ok()?;
+
+    Utc.timestamp_opt(claims.exp, 0).single()
+}
+
+fn default_path<F>(env: F) -> Option<PathBuf>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(path) = env("DROGUE_CONFIG").or_else(|| env("DRGCFG")) {
+        return Some(path.into());
+    }
+
+    let dir = match env("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env("HOME")?).join(".config"),
+    };
+
+    Some(dir.join(CONFIG_FILE_NAME))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::TransportResponse;
+    use reqwest::{header::HeaderMap, StatusCode};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    const CONFIG: &str = r#"
+active_context: sandbox
+contexts:
+  - name: sandbox
+    drogue_cloud_url: "https://api.sandbox.drogue.cloud"
+    default_app: my-app
+    token:
+      access_token: "eyJ..."
+      refresh_token: "eyR..."
+      expires_in: 300
+    auth_url: "https://sso.sandbox.drogue.cloud/realms/drogue"
+    registry_url: "https://api.sandbox.drogue.cloud"
+  - name: local
+    drogue_cloud_url: "http://localhost:8080"
+    token:
+      id: foo
+      token: "drg_..."
+"#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_active_context() {
+        let config = Config::from_yaml(CONFIG).unwrap();
+        let client = ClientConfig::from_config(&config, env(&[])).unwrap();
+
+        assert_eq!(client.api_url.as_str(), "https://api.sandbox.drogue.cloud/");
+        assert_eq!(client.default_app.as_deref(), Some("my-app"));
+        assert_eq!(
+            client.token,
+            Some(Token::Bearer {
+                access_token: "eyJ...".into(),
+                refresh_token: Some("eyR...".into()),
+            })
+        );
+        assert_eq!(
+            client.endpoints().sso.as_deref(),
+            Some("https://sso.sandbox.drogue.cloud/realms/drogue")
+        );
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let config = Config::from_yaml(CONFIG).unwrap();
+        let client = ClientConfig::from_config(
+            &config,
+            env(&[
+                ("DROGUE_CONTEXT", "local"),
+                ("DROGUE_APP", "other-app"),
+                ("DROGUE_TOKEN", "bearer"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(client.api_url.as_str(), "http://localhost:8080/");
+        assert_eq!(client.default_app.as_deref(), Some("other-app"));
+        assert_eq!(
+            client.token,
+            Some(Token::Bearer {
+                access_token: "bearer".into(),
+                refresh_token: None,
+            })
+        );
+
+        let result = ClientConfig::from_config(&config, env(&[("DROGUE_CONTEXT", "foo")]));
+        assert!(matches!(result, Err(ConfigError::UnknownContext(_))));
+    }
+
+    #[test]
+    fn test_env_only() {
+        let client = ClientConfig::from_config(
+            &Config::default(),
+            env(&[
+                ("DROGUE_API_URL", "https://api.example.com"),
+                ("DROGUE_USER", "foo"),
+                ("DROGUE_ACCESS_TOKEN", "drg_..."),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            client.token,
+            Some(Token::AccessToken {
+                id: "foo".into(),
+                token: "drg_...".into(),
+            })
+        );
+
+        let result = ClientConfig::from_config(&Config::default(), env(&[]));
+        assert!(matches!(result, Err(ConfigError::MissingApiUrl)));
+    }
+
+    #[test]
+    fn test_default_path() {
+        assert_eq!(
+            default_path(env(&[("HOME", "/home/foo")])),
+            Some(PathBuf::from("/home/foo/.config/drg_config.yaml"))
+        );
+        assert_eq!(
+            default_path(env(&[("HOME", "/home/foo"), ("DRGCFG", "/tmp/drg.yaml")])),
+            Some(PathBuf::from("/tmp/drg.yaml"))
+        );
+    }
+
+    fn jwt(expires: DateTime<Utc>) -> String {
+        let claims = json!({"exp": expires.timestamp()}).to_string();
+        format!(
+            "header.{}.signature",
+            base64::encode_config(claims, base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    /// A token endpoint, handing out a fresh token for a refresh token.
+    #[derive(Debug, Default)]
+    struct TokenEndpoint {
+        requests: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Transport for TokenEndpoint {
+        async fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> Result<TransportResponse, ClientError> {
+            let body = String::from_utf8(request.body.unwrap_or_default()).unwrap();
+            self.requests.lock().unwrap().push(body);
+
+            Ok(TransportResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: serde_json::to_vec(&json!({
+                    "access_token": jwt(Utc::now() + chrono::Duration::minutes(5)),
+                    "refresh_token": "refreshed",
+                }))
+                .unwrap(),
+            })
+        }
+    }
+
+    fn bearer(
+        expires: DateTime<Utc>,
+        refresh_token: Option<&str>,
+        token_url: bool,
+    ) -> ClientConfig {
+        ClientConfig {
+            api_url: Url::parse("https://api.example.com").unwrap(),
+            registry_url: None,
+            sso_url: None,
+            token_url: match token_url {
+                true => Some(Url::parse("https://sso.example.com/token").unwrap()),
+                false => None,
+            },
+            default_app: None,
+            token: Some(Token::Bearer {
+                access_token: jwt(expires),
+                refresh_token: refresh_token.map(Into::into),
+            }),
+        }
+    }
+
+    async fn provide(provider: &ConfigTokenProvider) -> Result<String, ClientError> {
+        match provider.provide_access_token().await? {
+            Some(Credentials::Bearer(token)) => Ok(token),
+            other => panic!("Unexpected credentials: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_valid() -> anyhow::Result<()> {
+        let expires = Utc::now() + chrono::Duration::minutes(5);
+        let endpoint = Arc::new(TokenEndpoint::default());
+        let provider = bearer(expires, Some("refresh"), true).token_provider_with(endpoint.clone());
+
+        assert_eq!(provide(&provider).await?, jwt(expires));
+        assert!(endpoint.requests.lock().unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_refresh() -> anyhow::Result<()> {
+        let expires = Utc::now() - chrono::Duration::minutes(5);
+        let endpoint = Arc::new(TokenEndpoint::default());
+        let provider = bearer(expires, Some("refresh"), true).token_provider_with(endpoint.clone());
+
+        let token = provide(&provider).await?;
+        assert_ne!(token, jwt(expires));
+        // the refreshed token is valid, and used for the next request
+        assert_eq!(provide(&provider).await?, token);
+
+        let requests = endpoint.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0],
+            "grant_type=refresh_token&refresh_token=refresh&client_id=drogue"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_expired() {
+        let expires = Utc::now() - chrono::Duration::minutes(5);
+        for (refresh_token, token_url) in [(None, true), (Some("refresh"), false)] {
+            let endpoint = Arc::new(TokenEndpoint::default());
+            let provider =
+                bearer(expires, refresh_token, token_url).token_provider_with(endpoint.clone());
+
+            assert!(matches!(
+                provide(&provider).await,
+                Err(ClientError::Token(_))
+            ));
+            assert!(endpoint.requests.lock().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use crate::error::ClientError;
+use std::{future::Future, sync::Arc};
+
+/// The runtime, driving the async clients of the blocking clients.
+///
+/// The runtime is cheap to clone, all clones share the same runtime.
+#[derive(Clone, Debug)]
+pub struct BlockingRuntime(Arc<tokio::runtime::Runtime>);
+
+impl BlockingRuntime {
+    /// Create a new runtime.
+    pub fn new() -> Result<Self, ClientError> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(|runtime| Self(Arc::new(runtime)))
+            .map_err(|err| ClientError::Request(format!("Failed to create runtime: {}", err)))
+    }
+
+    /// Run a future to completion, blocking the current thread.
+    ///
+    /// This must not be called from within an async context, otherwise it will panic.
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.0.block_on(future)
+    }
+}
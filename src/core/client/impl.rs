@@ -1,35 +1,132 @@
 use crate::{
-    core::PropagateCurrentContext,
-    error::{ClientError, ErrorInformation},
+    core::{v1::Patch, PropagateCurrentContext, Transport, TransportRequest, TransportResponse},
+    error::{ClientError, ErrorInformation, ResponseContext},
     openid::{TokenInjector, TokenProvider},
 };
 use async_trait::async_trait;
-use reqwest::{Response, StatusCode};
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Method, StatusCode,
+};
 use serde::{de::DeserializeOwned, Serialize};
-use std::marker::Send;
+use std::{marker::Send, time::Duration};
 use url::Url;
 
-/// A drogue HTTP client, backed by reqwest.
+/// A drogue HTTP client, backed by a [`Transport`].
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
 pub(crate) trait CoreClient {
-    /// Retrieve the http client
-    fn client(&self) -> &reqwest::Client;
+    /// Retrieve the name of the service, used e.g. for labeling metrics
+    #[cfg_attr(
+        not(all(feature = "telemetry", not(target_arch = "wasm32"))),
+        allow(dead_code)
+    )]
+    fn service(&self) -> &'static str;
+
+    /// Retrieve the transport
+    fn transport(&self) -> &dyn Transport;
 
     /// Retrieve the token provider
     fn token_provider(&self) -> &dyn TokenProvider;
 
+    /// Retrieve the timeout for requests
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Create a new request, using the settings of the client.
+    fn request(&self, method: Method, url: Url) -> TransportRequest {
+        let request = TransportRequest::new(method, url);
+        match self.timeout() {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Execute a request using the transport.
+    ///
+    /// The correct authentication and tracing headers will be added to the request. The timeout
+    /// of the client is a deadline for the whole call: acquiring the token, sending the request,
+    /// and all retries of the transport.
+    ///
+    /// The operation is the name of the client function, executing the request.
+    async fn execute(
+        &self,
+        operation: &'static str,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, ClientError>
+    where
+        Self: Send,
+    {
+        let request = request.propagate_current_context();
+
+        // a single deadline for the whole call, no matter if the transport honors the timeout
+        // of the request, or retries it
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = self.timeout() {
+            return tokio::time::timeout(timeout, async {
+                let request = request.inject_token(self.token_provider()).await?;
+                self.send(operation, request).await
+            })
+            .await
+            .map_err(|_| ClientError::Timeout)?;
+        }
+
+        let request = request.inject_token(self.token_provider()).await?;
+
+        self.send(operation, request).await
+    }
+
+    /// Send a request using the transport, as it is.
+    ///
+    /// With the `telemetry` feature, the request will be recorded in the HTTP metrics, labeled
+    /// with the service and the operation.
+    #[cfg(all(feature = "telemetry", not(target_arch = "wasm32")))]
+    async fn send(
+        &self,
+        operation: &'static str,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, ClientError>
+    where
+        Self: Send,
+    {
+        let start = std::time::Instant::now();
+        let result = self.transport().execute(request).await;
+
+        crate::metrics::record_http_request(
+            self.service(),
+            operation,
+            result.as_ref().ok().map(|response| response.status),
+            start.elapsed(),
+        );
+
+        result
+    }
+
+    /// Send a request using the transport, as it is.
+    #[cfg(not(all(feature = "telemetry", not(target_arch = "wasm32"))))]
+    async fn send(
+        &self,
+        _operation: &'static str,
+        request: TransportRequest,
+    ) -> Result<TransportResponse, ClientError>
+    where
+        Self: Send,
+    {
+        self.transport().execute(request).await
+    }
+
     /// Execute a GET request to read a resource content or to list resources
     ///
     /// The correct authentication and tracing headers will be added to the request.
     #[doc(hidden)]
-    async fn read<T>(&self, url: Url) -> Result<Option<T>, ClientError>
+    async fn read<T>(&self, operation: &'static str, url: Url) -> Result<Option<T>, ClientError>
     where
         Self: Send,
         T: DeserializeOwned,
     {
-        self.read_with_query_parameters(url, None).await
+        self.read_with_query_parameters(operation, url, None).await
     }
 
     /// Execute a GET request to read a resource content or to list resources
@@ -38,6 +135,7 @@ pub(crate) trait CoreClient {
     /// The correct authentication and tracing headers will be added to the request.
     async fn read_with_query_parameters<T>(
         &self,
+        operation: &'static str,
         url: Url,
         query: Option<Vec<(String, String)>>,
     ) -> Result<Option<T>, ClientError>
@@ -47,25 +145,20 @@ pub(crate) trait CoreClient {
     {
         let query = query.unwrap_or_default();
 
-        let req = self
-            .client()
-            .get(url)
-            .query(&query)
-            .propagate_current_context()
-            .inject_token(self.token_provider())
-            .await?;
+        let req = self.request(Method::GET, url).query(&query);
 
-        Self::read_response(req.send().await?).await
+        Self::read_response(RequestInfo::from(&req), self.execute(operation, req).await?).await
     }
 
     async fn read_response<T: DeserializeOwned>(
-        response: Response,
+        request: RequestInfo,
+        response: TransportResponse,
     ) -> Result<Option<T>, ClientError> {
         log::debug!("Eval get response: {:#?}", response);
-        match response.status() {
-            StatusCode::OK => Ok(Some(response.json().await?)),
+        match response.status {
+            StatusCode::OK => Ok(Some(response.json()?)),
             StatusCode::NOT_FOUND => Ok(None),
-            _ => Self::default_response(response).await,
+            _ => Self::default_response(request, response).await,
         }
     }
 
@@ -75,69 +168,103 @@ pub(crate) trait CoreClient {
     /// The resource must exist, otherwise `false` is returned.
     ///
     /// The correct authentication and tracing headers will be added to the request.
-    async fn update<A>(&self, url: Url, payload: Option<A>) -> Result<bool, ClientError>
+    async fn update<A>(
+        &self,
+        operation: &'static str,
+        url: Url,
+        payload: Option<A>,
+    ) -> Result<bool, ClientError>
     where
         Self: Send,
         A: Serialize + Send + Sync,
     {
+        let req = self.request(Method::PUT, url);
         let req = if let Some(p) = payload {
-            self.client().put(url).json(&p)
+            req.json(&p)?
         } else {
-            self.client().put(url)
-        }
-        .propagate_current_context()
-        .inject_token(self.token_provider())
-        .await?;
+            req
+        };
 
-        Self::update_response(req.send().await?).await
+        Self::update_response(RequestInfo::from(&req), self.execute(operation, req).await?).await
     }
 
-    async fn update_response(response: Response) -> Result<bool, ClientError> {
+    async fn update_response(
+        request: RequestInfo,
+        response: TransportResponse,
+    ) -> Result<bool, ClientError> {
         log::debug!("Eval update response: {:#?}", response);
-        match response.status() {
+        match response.status {
             StatusCode::OK | StatusCode::NO_CONTENT | StatusCode::ACCEPTED => Ok(true),
             StatusCode::NOT_FOUND => Ok(false),
-            _ => Self::default_response(response).await,
+            _ => Self::default_response(request, response).await,
         }
     }
 
-    /// Execute a DELETE request to delete an existing resource.
+    /// Execute a PATCH request to modify an existing resource.
     ///
+    /// The content type of the request is derived from the kind of patch.
     /// The resource must exist, otherwise `false` is returned.
     ///
     /// The correct authentication and tracing headers will be added to the request.
-    async fn delete(&self, url: Url) -> Result<bool, ClientError>
+    async fn patch(
+        &self,
+        operation: &'static str,
+        url: Url,
+        patch: &Patch,
+    ) -> Result<bool, ClientError>
     where
         Self: Send,
     {
         let req = self
-            .client()
-            .delete(url)
-            .inject_token(self.token_provider())
-            .await?;
+            .request(Method::PATCH, url)
+            .json(&patch.to_value()?)?
+            .header(CONTENT_TYPE, HeaderValue::from_static(patch.content_type()));
+
+        Self::update_response(RequestInfo::from(&req), self.execute(operation, req).await?).await
+    }
+
+    /// Execute a DELETE request to delete an existing resource.
+    ///
+    /// The resource must exist, otherwise `false` is returned.
+    ///
+    /// The correct authentication and tracing headers will be added to the request.
+    async fn delete(&self, operation: &'static str, url: Url) -> Result<bool, ClientError>
+    where
+        Self: Send,
+    {
+        let req = self.request(Method::DELETE, url);
 
-        Self::delete_response(req.send().await?).await
+        Self::delete_response(RequestInfo::from(&req), self.execute(operation, req).await?).await
     }
 
-    async fn delete_response(response: Response) -> Result<bool, ClientError> {
+    async fn delete_response(
+        request: RequestInfo,
+        response: TransportResponse,
+    ) -> Result<bool, ClientError> {
         log::debug!("Eval delete response: {:#?}", response);
-        match response.status() {
+        match response.status {
             StatusCode::OK | StatusCode::NO_CONTENT => Ok(true),
             StatusCode::NOT_FOUND => Ok(false),
-            _ => Self::default_response(response).await,
+            _ => Self::default_response(request, response).await,
         }
     }
 
     /// Execute a POST request to create a resource.
     ///
     /// The correct authentication and tracing headers will be added to the request.
-    async fn create<P, T>(&self, url: Url, payload: Option<P>) -> Result<Option<T>, ClientError>
+    async fn create<P, T>(
+        &self,
+        operation: &'static str,
+        url: Url,
+        payload: Option<P>,
+    ) -> Result<Option<T>, ClientError>
     where
         Self: Send,
         P: Serialize + Send + Sync,
         T: DeserializeOwned,
     {
-        self.create_with_query_parameters(url, payload, None).await
+        self.create_with_query_parameters(operation, url, payload, None)
+            .await
     }
 
     /// Execute a POST request to create a resource.
@@ -146,6 +273,7 @@ pub(crate) trait CoreClient {
     /// The correct authentication and tracing headers will be added to the request.
     async fn create_with_query_parameters<P, T>(
         &self,
+        operation: &'static str,
         url: Url,
         payload: Option<P>,
         query: Option<Vec<(String, String)>>,
@@ -157,36 +285,75 @@ pub(crate) trait CoreClient {
     {
         let query = query.unwrap_or_default();
 
+        let req = self.request(Method::POST, url).query(&query);
         let req = if let Some(p) = payload {
-            self.client().post(url).json(&p)
+            req.json(&p)?
         } else {
-            self.client().post(url)
-        }
-        .query(&query)
-        .propagate_current_context()
-        .inject_token(self.token_provider())
-        .await?;
+            req
+        };
 
-        Self::create_response(req.send().await?).await
+        Self::create_response(RequestInfo::from(&req), self.execute(operation, req).await?).await
     }
 
     async fn create_response<T: DeserializeOwned>(
-        response: Response,
+        request: RequestInfo,
+        response: TransportResponse,
     ) -> Result<Option<T>, ClientError> {
         log::debug!("Eval create response: {:#?}", response);
-        match response.status() {
+        match response.status {
             StatusCode::CREATED | StatusCode::ACCEPTED => Ok(None),
             // the token API responds 200 on token creations, sending back the content.
-            StatusCode::OK => Ok(Some(response.json().await?)),
-            _ => Self::default_response(response).await,
+            StatusCode::OK => Ok(Some(response.json()?)),
+            _ => Self::default_response(request, response).await,
+        }
+    }
+
+    async fn default_response<T>(
+        request: RequestInfo,
+        response: TransportResponse,
+    ) -> Result<T, ClientError> {
+        let code = response.status;
+        let context = Box::new(ResponseContext::new(
+            request.method.as_str(),
+            &request.url,
+            &response.body,
+            &response.headers,
+        ));
+
+        match (code, response.json::<ErrorInformation>()) {
+            (StatusCode::TOO_MANY_REQUESTS, info) => Err(ClientError::RateLimited {
+                retry_after: response.retry_after(),
+                error: info.ok(),
+                context,
+            }),
+            (StatusCode::CONFLICT, info) => Err(ClientError::Conflict {
+                error: info.unwrap_or_else(|_| ErrorInformation {
+                    error: "Conflict".to_string(),
+                    message: Default::default(),
+                }),
+                context,
+            }),
+            (_, Ok(info)) => Err(ClientError::Service {
+                code,
+                error: info,
+                context,
+            }),
+            (_, Err(_)) => Err(ClientError::Response { code, context }),
         }
     }
+}
+
+/// The information of a request, required to report errors of its response.
+pub(crate) struct RequestInfo {
+    method: Method,
+    url: Url,
+}
 
-    async fn default_response<T>(response: Response) -> Result<T, ClientError> {
-        let code = response.status();
-        match response.json::<ErrorInformation>().await {
-            Ok(info) => Err(ClientError::Service { code, error: info }),
-            Err(_) => Err(ClientError::Response(code)),
+impl From<&TransportRequest> for RequestInfo {
+    fn from(request: &TransportRequest) -> Self {
+        Self {
+            method: request.method.clone(),
+            url: request.url.clone(),
         }
     }
 }
@@ -0,0 +1,224 @@
+use crate::{
+    core::{Transport, TransportRequest, TransportResponse},
+    error::ClientError,
+};
+use async_trait::async_trait;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Hooks, called for every request passing a [`MiddlewareTransport`].
+///
+/// All hooks have a default implementation doing nothing, so that only the required ones need
+/// to be implemented.
+#[async_trait]
+pub trait Middleware: Send + Sync + fmt::Debug {
+    /// Called before the request is sent.
+    ///
+    /// The request may be modified, e.g. by adding headers. Returning an error aborts the
+    /// request, without calling any further hooks.
+    async fn before(&self, _request: &mut TransportRequest) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Called after a response was received, no matter what its status code is.
+    ///
+    /// The request is the one which was sent, including the modifications of all middleware.
+    /// The response may be modified. Returning an error fails the request, without calling any
+    /// further hooks.
+    async fn after(
+        &self,
+        _request: &TransportRequest,
+        _response: &mut TransportResponse,
+        _elapsed: Duration,
+    ) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Called when no response could be received.
+    async fn failed(&self, _request: &TransportRequest, _error: &ClientError, _elapsed: Duration) {}
+}
+
+/// A transport, passing all requests and responses through a chain of [`Middleware`].
+///
+/// The `before` hooks are called in the order the middleware was added, the `after` and
+/// `failed` hooks in the reverse order. Wrapping the transport of the service clients makes the
+/// middleware apply to all of them:
+///
+/// ```rust
+/// use drogue_client::{core::{Middleware, MiddlewareTransport, TransportRequest}, error::ClientError, openid::NoTokenProvider, DrogueClient};
+/// use async_trait::async_trait;
+/// use reqwest::header::HeaderValue;
+/// use url::Url;
+///
+/// #[derive(Debug)]
+/// struct Tenant(HeaderValue);
+///
+/// #[async_trait]
+/// impl Middleware for Tenant {
+///     async fn before(&self, request: &mut TransportRequest) -> Result<(), ClientError> {
+///         request.headers.insert("x-tenant-id", self.0.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let transport = MiddlewareTransport::new(reqwest::Client::new())
+///     .with(Tenant(HeaderValue::from_static("my-tenant")));
+/// let client = DrogueClient::with_endpoints(
+///     transport,
+///     Url::parse("http://localhost").unwrap(),
+///     Default::default(),
+///     NoTokenProvider,
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct MiddlewareTransport<T: Transport> {
+    transport: T,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl<T: Transport> MiddlewareTransport<T> {
+    /// Wrap a transport, without any middleware.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Add a middleware to the end of the chain.
+    pub fn with(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for MiddlewareTransport<T> {
+    async fn execute(
+        &self,
+        mut request: TransportRequest,
+    ) -> Result<TransportResponse, ClientError> {
+        for middleware in &self.middleware {
+            middleware.before(&mut request).await?;
+        }
+
+        let start = Instant::now();
+        let result = self.transport.execute(request.clone()).await;
+        let elapsed = start.elapsed();
+
+        match result {
+            Ok(mut response) => {
+                for middleware in self.middleware.iter().rev() {
+                    middleware.after(&request, &mut response, elapsed).await?;
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                for middleware in self.middleware.iter().rev() {
+                    middleware.failed(&request, &err, elapsed).await;
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        mock::MockServer,
+        openid::NoTokenProvider,
+        registry::v1::{Application, Client},
+    };
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    /// Adds a header, and records all calls.
+    #[derive(Debug)]
+    struct Recorder {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Recorder {
+        async fn before(&self, request: &mut TransportRequest) -> Result<(), ClientError> {
+            request
+                .headers
+                .insert(self.name, HeaderValue::from_static("1"));
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("before {}", self.name));
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            request: &TransportRequest,
+            response: &mut TransportResponse,
+            _: Duration,
+        ) -> Result<(), ClientError> {
+            self.calls.lock().unwrap().push(format!(
+                "after {} {} {}",
+                self.name,
+                response.status.as_u16(),
+                request.headers.len()
+            ));
+            Ok(())
+        }
+    }
+
+    /// Replaces the body of all responses.
+    #[derive(Debug)]
+    struct Redact;
+
+    #[async_trait]
+    impl Middleware for Redact {
+        async fn after(
+            &self,
+            _: &TransportRequest,
+            response: &mut TransportResponse,
+            _: Duration,
+        ) -> Result<(), ClientError> {
+            response.body = br#"{"metadata":{"name":"redacted"}}"#.to_vec();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorder = |name| Recorder {
+            name,
+            calls: calls.clone(),
+        };
+        let transport = MiddlewareTransport::new(server.clone())
+            .with(Redact)
+            .with(recorder("x-first"))
+            .with(recorder("x-second"));
+        let client = Client::new(transport, server.api_url(), NoTokenProvider);
+
+        let app = client.get_app("app").await?.unwrap();
+        assert_eq!(app.metadata.name, "redacted");
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "before x-first",
+                "before x-second",
+                "after x-second 200 2",
+                "after x-first 200 2",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,6 +1,24 @@
+#[cfg(all(any(test, feature = "blocking"), not(target_arch = "wasm32")))]
+mod blocking;
 mod r#impl;
+#[cfg(not(target_arch = "wasm32"))]
+mod middleware;
+mod options;
+mod record;
+#[cfg(not(target_arch = "wasm32"))]
+mod retry;
+mod transport;
 
-pub(crate) use r#impl::CoreClient;
+#[cfg(all(any(test, feature = "blocking"), not(target_arch = "wasm32")))]
+pub use blocking::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use middleware::*;
+pub use options::*;
+pub(crate) use r#impl::{CoreClient, RequestInfo};
+pub use record::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use retry::*;
+pub use transport::*;
 
 pub trait PropagateCurrentContext {
     fn propagate_current_context(self) -> Self
@@ -30,6 +48,28 @@ impl PropagateCurrentContext for reqwest::RequestBuilder {
     }
 }
 
+#[cfg(not(feature = "telemetry"))]
+impl PropagateCurrentContext for TransportRequest {
+    #[inline]
+    fn propagate_current_context(self) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl PropagateCurrentContext for TransportRequest {
+    #[inline]
+    fn propagate_current_context(self) -> Self
+    where
+        Self: Sized,
+    {
+        self.propagate_context(&opentelemetry::Context::current())
+    }
+}
+
 #[cfg(feature = "telemetry")]
 pub use self::tracing::*;
 
@@ -40,6 +80,8 @@ mod tracing {
     use opentelemetry::Context;
     use reqwest::RequestBuilder;
 
+    use super::TransportRequest;
+
     pub trait WithTracing {
         fn propagate_context(self, cx: &Context) -> Self;
     }
@@ -55,6 +97,18 @@ mod tracing {
         }
     }
 
+    impl WithTracing for TransportRequest {
+        fn propagate_context(mut self, cx: &Context) -> Self {
+            let headers = opentelemetry::global::get_text_map_propagator(|prop| {
+                let mut injector = HeaderInjector::new();
+                prop.inject_context(cx, &mut injector);
+                injector.0
+            });
+            self.headers.extend(headers);
+            self
+        }
+    }
+
     struct HeaderInjector(HeaderMap);
 
     impl HeaderInjector {
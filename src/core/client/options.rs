@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+/// Options for individual calls, overriding the settings of a client.
+///
+/// The service clients apply these with `with_options`, which creates a copy of the client for
+/// the call. The timeout can also be set for all calls of a client with `with_timeout`.
+///
+/// ```rust
+/// use drogue_client::{core::RequestOptions, openid::NoTokenProvider, registry};
+/// use std::time::Duration;
+/// use url::Url;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let client = registry::v1::Client::new(
+///     reqwest::Client::new(),
+///     Url::parse("http://localhost")?,
+///     NoTokenProvider,
+/// );
+/// let app = client
+///     .with_options(RequestOptions::new().timeout(Duration::from_secs(1)))
+///     .get_app("my-app")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestOptions {
+    /// The timeout for the call.
+    ///
+    /// This is a single deadline for the whole call, including acquiring a token, sending the
+    /// request, and all retries of the transport. It is enforced by the client, whether or not
+    /// the transport honors [`TransportRequest::timeout`](crate::core::TransportRequest). If
+    /// it expires, [`ClientError::Timeout`](crate::error::ClientError::Timeout) is returned.
+    pub timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    /// Create options, not overriding any settings.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the timeout for the call.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
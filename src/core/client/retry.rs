@@ -0,0 +1,305 @@
+use crate::{
+    core::{Transport, TransportRequest, TransportResponse},
+    error::ClientError,
+};
+use async_trait::async_trait;
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+/// A policy for retrying failed requests.
+///
+/// Requests are retried when the connection to the server fails, or when the server responds
+/// with `429 Too Many Requests`, `502 Bad Gateway`, `503 Service Unavailable`, or
+/// `504 Gateway Timeout`.
+///
+/// By default, only safe and idempotent requests (`GET`, `HEAD`, `OPTIONS`, `PUT`, `DELETE`)
+/// are retried. Retrying `POST` requests must be enabled using `retry_non_idempotent`.
+///
+/// The service clients created by a [`DrogueClient`](crate::DrogueClient) use the default
+/// policy, unless configured otherwise. Service clients created directly use the transport as
+/// it is, which can be wrapped in a [`RetryTransport`].
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the initial one.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The upper limit for the delay between two attempts.
+    pub max_backoff: Duration,
+    /// The factor by which the delay increases with each attempt.
+    pub multiplier: f64,
+    /// The fraction of the delay which gets randomized, between `0.0` and `1.0`.
+    pub jitter: f64,
+    /// Honor the `Retry-After` header sent by the server, up to `max_backoff`.
+    pub respect_retry_after: bool,
+    /// Also retry non-idempotent requests, like `POST`.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.5,
+            respect_retry_after: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Check if requests with this method may be retried.
+    pub fn is_retryable_method(&self, method: &Method) -> bool {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE => true,
+            _ => self.retry_non_idempotent,
+        }
+    }
+
+    /// Check if a response with this status code may be retried.
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Check if an error, reported by the transport, may be retried.
+    ///
+    /// See [`ClientError::is_retryable`].
+    pub fn is_retryable_error(&self, err: &ClientError) -> bool {
+        err.is_retryable()
+    }
+
+    /// Calculate the delay before the next attempt.
+    ///
+    /// The `attempt` is the number of the attempt which just failed, starting with `1`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let max = self.max_backoff.as_secs_f64();
+        // a negative or non-finite multiplier doesn't increase the delay
+        let multiplier = match self.multiplier {
+            multiplier if multiplier.is_finite() && multiplier >= 0.0 => multiplier,
+            _ => 1.0,
+        };
+        let exp = multiplier.powi(attempt.saturating_sub(1).min(i32::MAX as u32) as i32);
+        // the delay may overflow to infinity, which is capped by the maximum as well
+        let delay = (self.initial_backoff.as_secs_f64() * exp).min(max).max(0.0);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let delay = if jitter > 0.0 {
+            delay * (1.0 - jitter * rand::thread_rng().gen::<f64>())
+        } else {
+            delay
+        };
+
+        Duration::from_secs_f64(delay)
+    }
+}
+
+/// A transport, retrying failed requests according to a [`RetryPolicy`].
+///
+/// ```rust
+/// use drogue_client::{core::{RetryPolicy, RetryTransport}, openid::NoTokenProvider, registry};
+/// use url::Url;
+///
+/// let transport = RetryTransport::new(reqwest::Client::new(), RetryPolicy::default());
+/// let client = registry::v1::Client::new(
+///     transport,
+///     Url::parse("http://localhost").unwrap(),
+///     NoTokenProvider,
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct RetryTransport<T: Transport> {
+    transport: T,
+    policy: RetryPolicy,
+}
+
+impl<T: Transport> RetryTransport<T> {
+    /// Wrap a transport with a retry policy.
+    pub fn new(transport: T, policy: RetryPolicy) -> Self {
+        Self { transport, policy }
+    }
+
+    /// Get the retry policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    fn delay(&self, attempt: u32, response: Option<&TransportResponse>) -> Duration {
+        let retry_after = match response {
+            Some(response) if self.policy.respect_retry_after => response.retry_after(),
+            _ => None,
+        };
+
+        match retry_after {
+            Some(retry_after) => retry_after.min(self.policy.max_backoff),
+            None => self.policy.backoff(attempt),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for RetryTransport<T> {
+    async fn execute(&self, request: TransportRequest) -> Result<TransportResponse, ClientError> {
+        if !self.policy.is_retryable_method(&request.method) {
+            return self.transport.execute(request).await;
+        }
+
+        let mut attempt = 1;
+        loop {
+            let result = self.transport.execute(request.clone()).await;
+            let last = attempt >= self.policy.max_attempts;
+
+            let delay = match &result {
+                Ok(response) if !last && self.policy.is_retryable_status(response.status) => {
+                    self.delay(attempt, Some(response))
+                }
+                Err(err) if !last && self.policy.is_retryable_error(err) => {
+                    self.delay(attempt, None)
+                }
+                _ => return result,
+            };
+
+            log::debug!(
+                "Attempt {} of {} {} failed, retrying in {:?}",
+                attempt,
+                request.method,
+                request.url,
+                delay
+            );
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use url::Url;
+
+    #[derive(Debug)]
+    struct FakeTransport {
+        responses: Vec<StatusCode>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Transport for FakeTransport {
+        async fn execute(&self, _: TransportRequest) -> Result<TransportResponse, ClientError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_static("0"));
+            Ok(TransportResponse {
+                status: self.responses[call.min(self.responses.len() - 1)],
+                headers,
+                body: vec![],
+            })
+        }
+    }
+
+    fn fake(responses: Vec<StatusCode>) -> (RetryTransport<FakeTransport>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::default());
+        let transport = RetryTransport::new(
+            FakeTransport {
+                responses,
+                calls: calls.clone(),
+            },
+            RetryPolicy::default(),
+        );
+        (transport, calls)
+    }
+
+    fn request(method: Method) -> TransportRequest {
+        TransportRequest::new(method, Url::parse("http://localhost").unwrap())
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(20), Duration::from_secs(10));
+        assert_eq!(policy.backoff(100), Duration::from_secs(10));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+
+        for multiplier in [-2.0, f64::NAN, f64::INFINITY] {
+            let policy = RetryPolicy {
+                multiplier,
+                jitter: 0.0,
+                ..Default::default()
+            };
+            assert_eq!(policy.backoff(1), Duration::from_millis(100));
+            assert_eq!(policy.backoff(5), Duration::from_millis(100));
+        }
+
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let backoff = policy.backoff(2);
+            assert!(backoff >= Duration::from_millis(100));
+            assert!(backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let (transport, calls) = fake(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::OK,
+        ]);
+
+        let response = transport.execute(request(Method::GET)).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_exhausted() {
+        let (transport, calls) = fake(vec![StatusCode::BAD_GATEWAY]);
+
+        let response = transport.execute(request(Method::DELETE)).await.unwrap();
+        assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry() {
+        let (transport, calls) = fake(vec![StatusCode::INTERNAL_SERVER_ERROR]);
+        let response = transport.execute(request(Method::GET)).await.unwrap();
+        assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let (transport, calls) = fake(vec![StatusCode::SERVICE_UNAVAILABLE]);
+        let response = transport.execute(request(Method::POST)).await.unwrap();
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::error::ClientError;
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE, RETRY_AFTER},
+    Method, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, sync::Arc, time::Duration};
+use url::Url;
+
+/// An HTTP request, handed over to a [`Transport`].
+#[derive(Clone, Debug)]
+pub struct TransportRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+    /// The timeout for receiving the response.
+    pub timeout: Option<Duration>,
+}
+
+impl TransportRequest {
+    /// Create a new request, without headers or body.
+    pub fn new(method: Method, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    /// Append query parameters to the URL of the request.
+    pub fn query(mut self, query: &[(String, String)]) -> Self {
+        if !query.is_empty() {
+            self.url.query_pairs_mut().extend_pairs(query);
+        }
+        self
+    }
+
+    /// Set a header, replacing an existing value with the same name.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set the body to the JSON serialized payload, and set the content type accordingly.
+    pub fn json<P>(self, payload: &P) -> Result<Self, ClientError>
+    where
+        P: Serialize + ?Sized,
+    {
+        let body = serde_json::to_vec(payload)?;
+        Ok(self
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+            .body(body))
+    }
+
+    /// Set the raw body of the request.
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Set the timeout for receiving the response.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// An HTTP response, returned by a [`Transport`].
+#[derive(Clone)]
+pub struct TransportResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TransportResponse {
+    /// Deserialize the body of the response from JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ClientError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// Get the delay requested by the server using the `Retry-After` header.
+    ///
+    /// The header may either contain a number of seconds, or an HTTP date. A date in the past
+    /// results in a zero delay.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date = httpdate::parse_http_date(value).ok()?;
+        Some(
+            date.duration_since(std::time::SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl fmt::Debug for TransportResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .field("body", &format!("{} bytes", self.body.len()))
+            .finish()
+    }
+}
+
+/// The HTTP transport used by the service clients.
+///
+/// All service clients are built on top of this trait. It is implemented for [`reqwest::Client`],
+/// which is the default transport, but may be implemented by anything which can send a request
+/// and return a response, like a different HTTP stack, a middleware chain, or an in-process fake.
+///
+/// The transport only has to perform the actual HTTP exchange. Authentication and tracing
+/// headers are already part of the request when it gets passed in. If the request has a timeout,
+/// the transport should report its expiration as [`ClientError::Timeout`].
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait Transport: Send + Sync + fmt::Debug {
+    /// Execute the request, returning the response.
+    ///
+    /// Any response received from the server must be returned as `Ok`, no matter what its status
+    /// code is. An error must only be returned if no response could be received.
+    async fn execute(&self, request: TransportRequest) -> Result<TransportResponse, ClientError>;
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Transport for reqwest::Client {
+    async fn execute(&self, request: TransportRequest) -> Result<TransportResponse, ClientError> {
+        let mut req = self.request(request.method, request.url);
+        req = req.headers(request.headers);
+        if let Some(body) = request.body {
+            req = req.body(body);
+        }
+        // the wasm client of reqwest does not support timeouts
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = request.timeout {
+            req = req.timeout(timeout);
+        }
+
+        let response = req.send().await.map_err(map_reqwest_error)?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.map_err(map_reqwest_error)?.to_vec();
+
+        Ok(TransportResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+fn map_reqwest_error(err: reqwest::Error) -> ClientError {
+    if err.is_timeout() {
+        ClientError::Timeout
+    } else {
+        err.into()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl<T> Transport for Arc<T>
+where
+    T: Transport + ?Sized,
+{
+    async fn execute(&self, request: TransportRequest) -> Result<TransportResponse, ClientError> {
+        self.as_ref().execute(request).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let url = Url::parse("http://localhost/foo").unwrap();
+
+        let req = TransportRequest::new(Method::GET, url.clone()).query(&[]);
+        assert_eq!(req.url.as_str(), "http://localhost/foo");
+
+        let req = TransportRequest::new(Method::GET, url)
+            .query(&[("labels".to_string(), "foo=bar,baz".to_string())]);
+        assert_eq!(
+            req.url.as_str(),
+            "http://localhost/foo?labels=foo%3Dbar%2Cbaz"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let url = Url::parse("http://localhost/foo").unwrap();
+
+        let req = TransportRequest::new(Method::POST, url)
+            .json(&serde_json::json!({"foo": "bar"}))
+            .unwrap();
+
+        assert_eq!(
+            req.headers.get(CONTENT_TYPE).unwrap(),
+            HeaderValue::from_static("application/json")
+        );
+        assert_eq!(req.body.unwrap(), br#"{"foo":"bar"}"#);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut response = TransportResponse {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            headers: HeaderMap::new(),
+            body: vec![],
+        };
+        assert_eq!(response.retry_after(), None);
+
+        response
+            .headers
+            .insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(response.retry_after(), Some(Duration::from_secs(120)));
+
+        response.headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(response.retry_after(), Some(Duration::ZERO));
+
+        response
+            .headers
+            .insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(response.retry_after(), None);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // accepts connections, but never responds
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+
+        let request = TransportRequest::new(Method::GET, url).timeout(Duration::from_millis(100));
+        let result = Transport::execute(&reqwest::Client::new(), request).await;
+
+        assert!(matches!(result, Err(ClientError::Timeout)));
+    }
+}
@@ -0,0 +1,328 @@
+use super::{Patch, PatchOperation};
+use crate::{meta::v1::CommonMetadata, Translator};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+
+/// A single difference between two resources.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// The location of the difference, as JSON pointer ([RFC 6901]).
+    ///
+    /// [RFC 6901]: https://datatracker.ietf.org/doc/html/rfc6901
+    pub path: String,
+    /// The kind of difference.
+    pub kind: DiffKind,
+}
+
+/// The kind of a difference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    /// The value is only present in the second resource.
+    Added(Value),
+    /// The value is only present in the first resource.
+    Removed(Value),
+    /// The value is present in both resources, but differs.
+    Changed { from: Value, to: Value },
+}
+
+/// The differences between two resources.
+///
+/// Only the user managed parts of the metadata are compared: labels, annotations and
+/// finalizers. Server managed fields, like the `uid`, `generation` or the creation timestamp, are
+/// ignored. The `spec` and `status` sections are compared recursively, objects are walked into,
+/// while all other values (including arrays) are compared as a whole.
+///
+/// Empty labels, annotations, finalizers, `spec` and `status` sections are not present in the
+/// serialized resource. If such a section is empty in the first resource, it is reported as
+/// added as a whole, so that the resulting patch doesn't refer to a missing parent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub entries: Vec<DiffEntry>,
+}
+
+impl Diff {
+    /// Compute the differences between two resources.
+    pub fn new<T>(from: &T, to: &T) -> Self
+    where
+        T: AsRef<dyn CommonMetadata> + Translator,
+    {
+        let mut diff = Self::default();
+
+        let (from_meta, to_meta) = (from.as_ref(), to.as_ref());
+        diff.strings("/metadata/labels", from_meta.labels(), to_meta.labels());
+        diff.strings(
+            "/metadata/annotations",
+            from_meta.annotations(),
+            to_meta.annotations(),
+        );
+        match (from_meta.finalizers(), to_meta.finalizers()) {
+            (from, to) if from == to => {}
+            (from, to) if from.is_empty() => diff.entries.push(DiffEntry {
+                path: "/metadata/finalizers".into(),
+                kind: DiffKind::Added(to.clone().into()),
+            }),
+            (from, to) if to.is_empty() => diff.entries.push(DiffEntry {
+                path: "/metadata/finalizers".into(),
+                kind: DiffKind::Removed(from.clone().into()),
+            }),
+            (from, to) => diff.entries.push(DiffEntry {
+                path: "/metadata/finalizers".into(),
+                kind: DiffKind::Changed {
+                    from: from.clone().into(),
+                    to: to.clone().into(),
+                },
+            }),
+        }
+
+        diff.section("/spec", from.spec(), to.spec());
+        diff.section("/status", from.status(), to.status());
+
+        diff
+    }
+
+    /// Check if there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the differences below a path, e.g. `/spec`.
+    pub fn below<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a DiffEntry> {
+        self.entries.iter().filter(move |entry| {
+            entry.path == path
+                || (entry.path.starts_with(path) && entry.path[path.len()..].starts_with('/'))
+        })
+    }
+
+    /// Convert into a JSON patch, which transforms the first resource into the second one.
+    pub fn to_patch(&self) -> Patch {
+        Patch::Json(
+            self.entries
+                .iter()
+                .map(|entry| match &entry.kind {
+                    DiffKind::Added(value) => PatchOperation::Add {
+                        path: entry.path.clone(),
+                        value: value.clone(),
+                    },
+                    DiffKind::Removed(_) => PatchOperation::Remove {
+                        path: entry.path.clone(),
+                    },
+                    DiffKind::Changed { to, .. } => PatchOperation::Replace {
+                        path: entry.path.clone(),
+                        value: to.clone(),
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    fn strings(
+        &mut self,
+        path: &str,
+        from: &HashMap<String, String>,
+        to: &HashMap<String, String>,
+    ) {
+        let from: Map<_, _> = from
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+        let to: Map<_, _> = to
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().into()))
+            .collect();
+
+        self.section(path, &from, &to);
+    }
+
+    /// Compare a section, which is missing when it is empty.
+    fn section(&mut self, path: &str, from: &Map<String, Value>, to: &Map<String, Value>) {
+        if from.is_empty() && !to.is_empty() {
+            self.entries.push(DiffEntry {
+                path: path.into(),
+                kind: DiffKind::Added(Value::Object(to.clone())),
+            });
+        } else {
+            self.objects(path.into(), from, to);
+        }
+    }
+
+    fn objects(&mut self, path: String, from: &Map<String, Value>, to: &Map<String, Value>) {
+        let keys: BTreeSet<_> = from.keys().chain(to.keys()).collect();
+
+        for key in keys {
+            let path = format!("{}/{}", path, escape_pointer(key));
+            match (from.get(key), to.get(key)) {
+                (Some(from), None) => self.entries.push(DiffEntry {
+                    path,
+                    kind: DiffKind::Removed(from.clone()),
+                }),
+                (None, Some(to)) => self.entries.push(DiffEntry {
+                    path,
+                    kind: DiffKind::Added(to.clone()),
+                }),
+                (Some(Value::Object(from)), Some(Value::Object(to))) => {
+                    self.objects(path, from, to)
+                }
+                (Some(from), Some(to)) if from != to => self.entries.push(DiffEntry {
+                    path,
+                    kind: DiffKind::Changed {
+                        from: from.clone(),
+                        to: to.clone(),
+                    },
+                }),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Escape a key for use as a segment of a JSON pointer.
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        mock::MockServer,
+        openid::NoTokenProvider,
+        registry::v1::{Application, Client, Device},
+    };
+    use serde_json::json;
+
+    fn device(value: Value) -> Device {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_diff() {
+        let from = device(json!({
+            "metadata": {
+                "application": "app",
+                "name": "foo",
+                "uid": "1",
+                "generation": 1,
+                "labels": {"a": "1", "b": "2"},
+                "finalizers": ["x"],
+            },
+            "spec": {
+                "credentials": {"credentials": [{"pass": "foo"}]},
+                "gatewaySelector": {"matchNames": ["gw1"]},
+                "foo/bar": {"a": 1, "b": {"c": true}},
+            },
+        }));
+        let to = device(json!({
+            "metadata": {
+                "application": "app",
+                "name": "foo",
+                "uid": "2",
+                "generation": 5,
+                "labels": {"a": "1", "c": "3"},
+                "annotations": {"note": "x"},
+                "finalizers": ["x"],
+            },
+            "spec": {
+                "credentials": {"credentials": [{"pass": "bar"}]},
+                "foo/bar": {"a": 2, "b": {"c": true}},
+            },
+            "status": {"state": "ok"},
+        }));
+
+        let diff = Diff::new(&from, &to);
+
+        assert_eq!(
+            diff.entries,
+            vec![
+                DiffEntry {
+                    path: "/metadata/labels/b".into(),
+                    kind: DiffKind::Removed(json!("2")),
+                },
+                DiffEntry {
+                    path: "/metadata/labels/c".into(),
+                    kind: DiffKind::Added(json!("3")),
+                },
+                DiffEntry {
+                    path: "/metadata/annotations".into(),
+                    kind: DiffKind::Added(json!({"note": "x"})),
+                },
+                DiffEntry {
+                    path: "/spec/credentials/credentials".into(),
+                    kind: DiffKind::Changed {
+                        from: json!([{"pass": "foo"}]),
+                        to: json!([{"pass": "bar"}]),
+                    },
+                },
+                DiffEntry {
+                    path: "/spec/foo~1bar/a".into(),
+                    kind: DiffKind::Changed {
+                        from: json!(1),
+                        to: json!(2),
+                    },
+                },
+                DiffEntry {
+                    path: "/spec/gatewaySelector".into(),
+                    kind: DiffKind::Removed(json!({"matchNames": ["gw1"]})),
+                },
+                DiffEntry {
+                    path: "/status".into(),
+                    kind: DiffKind::Added(json!({"state": "ok"})),
+                },
+            ]
+        );
+
+        assert_eq!(diff.below("/spec").count(), 3);
+        assert_eq!(diff.below("/metadata/labels").count(), 2);
+        assert_eq!(diff.below("/meta").count(), 0);
+
+        assert_eq!(
+            diff.to_patch().to_value().unwrap()[0],
+            json!({"op": "remove", "path": "/metadata/labels/b"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_patch() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider);
+
+        let empty = Device::new("app", "foo");
+        let full = device(json!({
+            "metadata": {
+                "application": "app",
+                "name": "foo",
+                "labels": {"a": "1"},
+                "annotations": {"note": "x"},
+                "finalizers": ["x"],
+            },
+            "spec": {"foo": {"bar": true}},
+        }));
+        let mut changed = full.clone();
+        changed.metadata.labels.insert("b".into(), "2".into());
+        changed.metadata.finalizers.push("y".into());
+        changed.spec.insert("baz".into(), json!(1));
+
+        for (from, to) in [(&empty, &full), (&full, &changed), (&changed, &empty)] {
+            server.add_device(from.clone());
+            assert!(
+                client
+                    .patch_device("app", "foo", &Diff::new(from, to).to_patch())
+                    .await?
+            );
+            let patched = client.get_device("app", "foo").await?.unwrap();
+            assert!(Diff::new(&patched, to).is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_diff() {
+        let from = Device::new("app", "foo");
+        let mut to = from.clone();
+        to.metadata.uid = "1234".into();
+        to.metadata.resource_version = "1".into();
+
+        assert!(Diff::new(&from, &to).is_empty());
+    }
+}
@@ -1,3 +1,9 @@
+mod diff;
+mod patch;
+
+pub use diff::*;
+pub use patch::*;
+
 use crate::{Dialect, Section};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
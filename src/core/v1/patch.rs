@@ -0,0 +1,149 @@
+use super::escape_pointer;
+use crate::{Dialect, Section};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A patch, modifying parts of a resource.
+///
+/// A patch can either be a JSON merge patch ([RFC 7386]), or a JSON patch ([RFC 6902]).
+///
+/// [RFC 7386]: https://datatracker.ietf.org/doc/html/rfc7386
+/// [RFC 6902]: https://datatracker.ietf.org/doc/html/rfc6902
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Patch {
+    /// A JSON merge patch.
+    Merge(Value),
+    /// A JSON patch, consisting of a list of operations.
+    Json(Vec<PatchOperation>),
+}
+
+/// A single operation of a JSON patch.
+///
+/// The `path` and `from` fields are JSON pointers ([RFC 6901]).
+///
+/// [RFC 6901]: https://datatracker.ietf.org/doc/html/rfc6901
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl Patch {
+    /// The content type of the patch.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Merge(_) => "application/merge-patch+json",
+            Self::Json(_) => "application/json-patch+json",
+        }
+    }
+
+    /// Serialize the patch into a JSON value.
+    pub fn to_value(&self) -> Result<Value, serde_json::Error> {
+        match self {
+            Self::Merge(patch) => Ok(patch.clone()),
+            Self::Json(operations) => serde_json::to_value(operations),
+        }
+    }
+
+    /// Create a merge patch, which sets a strongly typed section.
+    ///
+    /// Fields of the section, which are not present in the serialized form, will not be
+    /// modified. Use [`Patch::replace_section`] to replace the section as a whole.
+    pub fn merge_section<D>(dialect: &D) -> Result<Self, serde_json::Error>
+    where
+        D: Serialize + Dialect,
+    {
+        Ok(Self::Merge(json!({
+            section_name(D::section()): {
+                D::key(): serde_json::to_value(dialect)?,
+            }
+        })))
+    }
+
+    /// Create a JSON patch, which replaces a strongly typed section, creating it if it does not
+    /// exist.
+    pub fn replace_section<D>(dialect: &D) -> Result<Self, serde_json::Error>
+    where
+        D: Serialize + Dialect,
+    {
+        Ok(Self::Json(vec![PatchOperation::Add {
+            path: section_pointer::<D>(),
+            value: serde_json::to_value(dialect)?,
+        }]))
+    }
+
+    /// Create a merge patch, which removes a strongly typed section.
+    pub fn clear_section<D>() -> Self
+    where
+        D: Dialect,
+    {
+        Self::Merge(json!({
+            section_name(D::section()): {
+                D::key(): Value::Null,
+            }
+        }))
+    }
+}
+
+fn section_name(section: Section) -> &'static str {
+    match section {
+        Section::Spec => "spec",
+        Section::Status => "status",
+    }
+}
+
+/// Create the JSON pointer to the section of a dialect.
+fn section_pointer<D: Dialect>() -> String {
+    format!(
+        "/{}/{}",
+        section_name(D::section()),
+        escape_pointer(D::key())
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialect;
+
+    #[derive(Serialize)]
+    struct Foo {
+        bar: String,
+    }
+
+    dialect!(Foo [Section::Spec => "foo/baz"]);
+
+    #[test]
+    fn test_merge_section() {
+        let patch = Patch::merge_section(&Foo { bar: "1".into() }).unwrap();
+        assert_eq!(patch.content_type(), "application/merge-patch+json");
+        assert_eq!(
+            patch.to_value().unwrap(),
+            json!({"spec": {"foo/baz": {"bar": "1"}}})
+        );
+    }
+
+    #[test]
+    fn test_replace_section() {
+        let patch = Patch::replace_section(&Foo { bar: "1".into() }).unwrap();
+        assert_eq!(patch.content_type(), "application/json-patch+json");
+        assert_eq!(
+            patch.to_value().unwrap(),
+            json!([{"op": "add", "path": "/spec/foo~1baz", "value": {"bar": "1"}}])
+        );
+    }
+
+    #[test]
+    fn test_clear_section() {
+        let patch = Patch::clear_section::<Foo>();
+        assert_eq!(
+            patch.to_value().unwrap(),
+            json!({"spec": {"foo/baz": null}})
+        );
+    }
+}
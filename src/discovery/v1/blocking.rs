@@ -0,0 +1,112 @@
+//! A blocking variant of the discovery client.
+
+use super::{DrogueVersion, Endpoints};
+use crate::{
+    core::{BlockingRuntime, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+};
+use std::time::Duration;
+use url::Url;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A blocking endpoint discovery client.
+///
+/// This wraps the async [`Client`](super::Client), and runs its operations on a
+/// [`BlockingRuntime`]. The operations must not be called from within an async context.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client,
+    runtime: BlockingRuntime,
+}
+
+impl Client {
+    /// Create a new unauthenticated client instance, using its own runtime.
+    pub fn new_anonymous(transport: impl Transport + 'static, api_url: Url) -> ClientResult<Self> {
+        Self::from_async(super::Client::new_anonymous(transport, api_url))
+    }
+
+    /// Create a new authenticated client instance, using its own runtime.
+    pub fn new_authenticated(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        Self::from_async(super::Client::new_authenticated(
+            transport,
+            api_url,
+            token_provider,
+        ))
+    }
+
+    /// Wrap an async client, using its own runtime.
+    pub fn from_async(client: super::Client) -> ClientResult<Self> {
+        Ok(Self::with_runtime(client, BlockingRuntime::new()?))
+    }
+
+    /// Wrap an async client, using an existing runtime.
+    pub fn with_runtime(client: super::Client, runtime: BlockingRuntime) -> Self {
+        Self {
+            inner: client,
+            runtime,
+        }
+    }
+
+    /// The wrapped async client.
+    pub fn inner(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// Set a timeout for requests made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.with_timeout(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Blocking variant of [`super::Client::get_public_endpoints`].
+    pub fn get_public_endpoints(&self) -> ClientResult<Option<Endpoints>> {
+        self.runtime.block_on(self.inner.get_public_endpoints())
+    }
+
+    /// Blocking variant of [`super::Client::get_authenticated_endpoints`].
+    pub fn get_authenticated_endpoints(&self) -> ClientResult<Option<Endpoints>> {
+        self.runtime
+            .block_on(self.inner.get_authenticated_endpoints())
+    }
+
+    /// Blocking variant of [`super::Client::get_drogue_cloud_version`].
+    pub fn get_drogue_cloud_version(&self) -> ClientResult<Option<DrogueVersion>> {
+        self.runtime.block_on(self.inner.get_drogue_cloud_version())
+    }
+
+    /// Blocking variant of [`super::Client::get_sso_url`].
+    pub fn get_sso_url(&self) -> ClientResult<Option<Url>> {
+        self.runtime.block_on(self.inner.get_sso_url())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::MockServer;
+
+    #[test]
+    fn test_blocking() -> anyhow::Result<()> {
+        let server = MockServer::new().with_version("1.0.0");
+        let client = Client::new_anonymous(server.clone(), server.api_url())?;
+
+        assert_eq!(client.get_drogue_cloud_version()?.unwrap().version, "1.0.0");
+        assert!(client.get_public_endpoints()?.unwrap().sso.is_some());
+
+        Ok(())
+    }
+}
@@ -1,53 +1,81 @@
 use super::data::*;
-use crate::core::CoreClient;
+use crate::core::{CoreClient, RequestInfo, RequestOptions, Transport};
 use crate::error::ClientError;
 use crate::openid::{NoTokenProvider, TokenProvider};
+use reqwest::Method;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::instrument;
 use url::Url;
 
 /// A client to discover available drogue-cloud endpoints and their URL.
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     api_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
 }
 
 type ClientResult<T> = Result<T, ClientError>;
 
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "discovery"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new unauthenticated client instance.
-    pub fn new_anonymous(client: reqwest::Client, api_url: Url) -> Self {
+    pub fn new_anonymous(transport: impl Transport + 'static, api_url: Url) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             api_url,
             token_provider: Arc::new(NoTokenProvider),
+            timeout: None,
         }
     }
 
     /// Create a new authenticated client instance.
     pub fn new_authenticated(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         api_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             api_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+        }
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
         }
+        client
     }
 
     fn url(&self, authenticated: bool) -> ClientResult<Url> {
@@ -72,24 +100,33 @@ impl Client {
     /// This endpoint does not require authentication, therefore the returned list of endpoint is not complete.
     #[instrument]
     pub async fn get_public_endpoints(&self) -> ClientResult<Option<Endpoints>> {
-        let req = self.client().get(self.url(false)?);
+        let req = self.request(Method::GET, self.url(false)?);
 
-        Self::read_response(req.send().await?).await
+        Self::read_response(
+            RequestInfo::from(&req),
+            self.send("get_public_endpoints", req).await?,
+        )
+        .await
     }
 
     /// Fetch drogue full list of accessible endpoints.
     #[instrument]
     pub async fn get_authenticated_endpoints(&self) -> ClientResult<Option<Endpoints>> {
-        self.read(self.url(true)?).await
+        self.read("get_authenticated_endpoints", self.url(true)?)
+            .await
     }
 
     /// Fetch drogue-cloud running version.
     #[instrument]
     pub async fn get_drogue_cloud_version(&self) -> ClientResult<Option<DrogueVersion>> {
         let url = self.api_url.join(".well-known/drogue-version")?;
-        let req = self.client().get(url);
+        let req = self.request(Method::GET, url);
 
-        Self::read_response(req.send().await?).await
+        Self::read_response(
+            RequestInfo::from(&req),
+            self.send("get_drogue_cloud_version", req).await?,
+        )
+        .await
     }
 
     /// Fetch drogue-cloud Single Sign On provider URL.
@@ -108,14 +145,12 @@ impl Client {
 #[cfg(test)]
 mod test {
     use crate::discovery::v1::Client;
-    use url::Url;
+    use crate::mock::MockServer;
 
     #[tokio::test]
     async fn test_get_drogue_version() {
-        let client: Client = Client::new_anonymous(
-            reqwest::Client::new(),
-            Url::parse("https://api.sandbox.drogue.cloud").unwrap(),
-        );
+        let server = MockServer::new();
+        let client: Client = Client::new_anonymous(server.clone(), server.api_url());
 
         let version = client.get_drogue_cloud_version().await;
         assert!(version.is_ok());
@@ -128,10 +163,8 @@ mod test {
 
     #[tokio::test]
     async fn test_get_drogue_public_endpoints() {
-        let client: Client = Client::new_anonymous(
-            reqwest::Client::new(),
-            Url::parse("https://api.sandbox.drogue.cloud").unwrap(),
-        );
+        let server = MockServer::new();
+        let client: Client = Client::new_anonymous(server.clone(), server.api_url());
 
         let endpoints = client.get_public_endpoints().await;
         assert!(endpoints.is_ok());
@@ -2,18 +2,30 @@
 
 pub mod admin;
 pub mod command;
+#[cfg(any(test, feature = "config"))]
+pub mod config;
 pub mod core;
 pub mod discovery;
 pub mod error;
 pub mod integration;
 pub mod meta;
 pub mod metrics;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod openid;
 pub mod registry;
 pub mod tokens;
 pub mod user;
 
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+mod builder;
+#[cfg(feature = "reqwest")]
+mod client;
 mod serde;
 mod translator;
 
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub use builder::*;
+#[cfg(feature = "reqwest")]
+pub use client::*;
 pub use translator::*;
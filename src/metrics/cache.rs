@@ -0,0 +1,19 @@
+use super::register;
+use prometheus::{IntCounterVec, Opts};
+
+lazy_static::lazy_static! {
+    pub static ref CACHE_LOOKUPS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("drogue_client_cache_lookups", "Lookups of cached resources"),
+            &["cache", "outcome"]
+        )
+        .unwrap()
+    );
+}
+
+/// Record the outcome of a cache lookup, labeled with the name of the cache.
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    CACHE_LOOKUPS
+        .with_label_values(&[cache, if hit { "hit" } else { "miss" }])
+        .inc();
+}
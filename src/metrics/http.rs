@@ -0,0 +1,80 @@
+use super::register;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+lazy_static::lazy_static! {
+    pub static ref HTTP_REQUESTS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("drogue_client_http_requests_total", "HTTP requests made by clients"),
+            &["service", "operation", "status"]
+        )
+        .unwrap()
+    );
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "drogue_client_http_request_duration_seconds",
+                "Latency of HTTP requests made by clients"
+            ),
+            &["service", "operation", "status"]
+        )
+        .unwrap()
+    );
+}
+
+/// Record an HTTP request, labeled with the service, the operation, and the status class.
+///
+/// The operation is the name of the client function sending the request (e.g. `get_device`). The
+/// status class is e.g. `2xx`, or `error` in case no response was received.
+pub fn record_http_request(
+    service: &str,
+    operation: &str,
+    status: Option<StatusCode>,
+    elapsed: Duration,
+) {
+    let status = match status {
+        Some(status) => format!("{}xx", status.as_u16() / 100),
+        None => "error".to_string(),
+    };
+    let labels = [service, operation, status.as_str()];
+
+    HTTP_REQUESTS.with_label_values(&labels).inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(elapsed.as_secs_f64());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        mock::MockServer,
+        openid::NoTokenProvider,
+        registry::v1::{Application, Client},
+    };
+
+    #[tokio::test]
+    async fn test_record() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider);
+
+        let created = HTTP_REQUESTS.with_label_values(&["registry", "create_app", "2xx"]);
+        let missing = HTTP_REQUESTS.with_label_values(&["registry", "get_app", "4xx"]);
+        let (before_created, before_missing) = (created.get(), missing.get());
+
+        client.create_app(&Application::new("app")).await?;
+        assert!(client.get_app("other").await?.is_none());
+
+        assert!(created.get() > before_created);
+        assert!(missing.get() > before_missing);
+        assert!(
+            HTTP_REQUEST_DURATION
+                .with_label_values(&["registry", "create_app", "2xx"])
+                .get_sample_count()
+                > 0
+        );
+
+        Ok(())
+    }
+}
@@ -1,9 +1,21 @@
 //! Metrics support for clients
 
+#[cfg(feature = "telemetry")]
+mod cache;
 #[cfg(feature = "telemetry")]
 mod ext;
+#[cfg(feature = "telemetry")]
+mod http;
 mod pass;
+#[cfg(feature = "telemetry")]
+mod registry;
 
+#[cfg(feature = "telemetry")]
+pub use cache::*;
 #[cfg(feature = "telemetry")]
 pub use ext::*;
+#[cfg(feature = "telemetry")]
+pub use http::*;
 pub use pass::*;
+#[cfg(feature = "telemetry")]
+pub use registry::*;
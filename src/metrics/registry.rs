@@ -0,0 +1,60 @@
+use prometheus::{core::Collector, Registry};
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref REGISTRY: Mutex<State> = Mutex::new(State {
+        registry: None,
+        used: false,
+    });
+}
+
+struct State {
+    registry: Option<Registry>,
+    used: bool,
+}
+
+/// Use a custom registry for all metrics of the clients.
+///
+/// By default, metrics are registered with the default registry of the `prometheus` crate. As
+/// metrics get registered when they are used for the first time, this must be called before
+/// using any client. Otherwise, the registry is handed back as an error.
+pub fn set_registry(registry: Registry) -> Result<(), Registry> {
+    let mut state = REGISTRY.lock().unwrap();
+    if state.used {
+        return Err(registry);
+    }
+    state.registry = Some(registry);
+    Ok(())
+}
+
+/// The registry the metrics of the clients get registered with.
+pub fn registry() -> Registry {
+    let mut state = REGISTRY.lock().unwrap();
+    state.used = true;
+    state
+        .registry
+        .get_or_insert_with(|| prometheus::default_registry().clone())
+        .clone()
+}
+
+/// Register a collector with the registry of the clients.
+pub(crate) fn register<C>(collector: C) -> C
+where
+    C: Collector + Clone + 'static,
+{
+    if let Err(err) = registry().register(Box::new(collector.clone())) {
+        log::warn!("Failed to register metrics: {}", err);
+    }
+    collector
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_registry_after_use() {
+        registry();
+        assert!(set_registry(Registry::new()).is_err());
+    }
+}
@@ -0,0 +1,1051 @@
+//! An in-memory mock of the Drogue Cloud API.
+//!
+//! The [`MockServer`] implements the [`Transport`] trait, and so can be used with any of the
+//! service clients in place of a real HTTP client. It keeps all state in memory, and answers
+//! requests to the registry, command, admin, tokens, user and discovery endpoints with the same
+//! status codes and payloads as Drogue Cloud does. This allows testing code using this crate,
+//! without a running cluster or network access.
+//!
+//! The host part of request URLs is ignored, only the path is used for routing the request. The
+//! server can be cloned cheaply, all clones share the same state. This allows to inspect the state
+//! of the server, e.g. the recorded commands, after the client processed its requests.
+//!
+//! This module requires the `mock` feature.
+
+mod registry;
+
+use crate::{
+    admin::v1::{Members, Role, TransferOwnership},
+    core::{Transport, TransportRequest, TransportResponse},
+    discovery::v1::{DrogueVersion, Endpoints, HttpEndpoint, MqttEndpoint, RegistryEndpoint},
+    error::{ClientError, ErrorInformation},
+    openid::TokenProvider,
+    registry::v1::{Application, Device},
+    tokens::v1::{AccessToken, CreatedAccessToken},
+    user::v1::{
+        authn::{AuthenticationRequest, AuthenticationResponse, Outcome as AuthnOutcome},
+        authz::{AuthorizationRequest, AuthorizationResponse, Outcome as AuthzOutcome, Permission},
+        UserDetails,
+    },
+    DrogueClient,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use percent_encoding::percent_decode_str;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use url::Url;
+
+/// The default API URL of the mock server.
+pub const MOCK_API_URL: &str = "http://localhost:8080";
+
+/// The user acting on unauthenticated requests, unless configured differently.
+pub const DEFAULT_USER: &str = "mock-user";
+
+/// A command, received by the mock server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockCommand {
+    pub application: String,
+    pub device: String,
+    pub command: String,
+    pub payload: Option<Vec<u8>>,
+}
+
+/// An in-memory mock of the Drogue Cloud API.
+///
+/// See the [module documentation](self) for more information.
+#[derive(Clone, Debug)]
+pub struct MockServer {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    api_url: Url,
+    endpoints: Endpoints,
+    version: String,
+    authentication: bool,
+    default_user: String,
+    counter: u64,
+    tokens: BTreeMap<String, Vec<Token>>,
+    apps: BTreeMap<String, AppEntry>,
+    devices: BTreeMap<String, BTreeMap<String, Device>>,
+    commands: Vec<MockCommand>,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    info: AccessToken,
+    token: String,
+}
+
+#[derive(Clone, Debug)]
+struct AppEntry {
+    app: Application,
+    owner: String,
+    members: Members,
+    transfer: Option<String>,
+}
+
+impl Default for MockServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockServer {
+    /// Create a new, empty, mock server, using [`MOCK_API_URL`] as API URL.
+    pub fn new() -> Self {
+        let api_url = Url::parse(MOCK_API_URL).expect("Mock API URL must be valid");
+
+        Self {
+            state: Arc::new(Mutex::new(State {
+                endpoints: default_endpoints(&api_url),
+                api_url,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                authentication: false,
+                default_user: DEFAULT_USER.to_string(),
+                counter: 0,
+                tokens: Default::default(),
+                apps: Default::default(),
+                devices: Default::default(),
+                commands: Default::default(),
+            })),
+        }
+    }
+
+    /// Set the endpoints, reported by the discovery endpoints.
+    pub fn with_endpoints(self, endpoints: Endpoints) -> Self {
+        self.state().endpoints = endpoints;
+        self
+    }
+
+    /// Set the version, reported by the discovery endpoint.
+    pub fn with_version<V>(self, version: V) -> Self
+    where
+        V: Into<String>,
+    {
+        self.state().version = version.into();
+        self
+    }
+
+    /// Require requests to be authenticated.
+    ///
+    /// By default, unauthenticated requests are processed on behalf of the default user. If
+    /// authentication is required, they are rejected with `401 Unauthorized` instead.
+    ///
+    /// Requests using basic authentication are always checked against the access tokens known
+    /// to the server. Bearer tokens are not validated, and are processed on behalf of the
+    /// default user.
+    pub fn require_authentication(self, authentication: bool) -> Self {
+        self.state().authentication = authentication;
+        self
+    }
+
+    /// Set the user, which is used for requests without a specific user.
+    ///
+    /// Defaults to [`DEFAULT_USER`].
+    pub fn with_default_user<U>(self, user: U) -> Self
+    where
+        U: Into<String>,
+    {
+        self.state().default_user = user.into();
+        self
+    }
+
+    /// The API URL of the server.
+    pub fn api_url(&self) -> Url {
+        self.state().api_url.clone()
+    }
+
+    /// The (full) endpoints, reported by the discovery endpoints.
+    pub fn endpoints(&self) -> Endpoints {
+        self.state().endpoints.clone()
+    }
+
+    /// The URL of the authentication endpoint of the user service.
+    pub fn authn_url(&self) -> Url {
+        self.api_url()
+            .join("api/user/v1alpha1/authn")
+            .expect("Authentication URL must be valid")
+    }
+
+    /// The URL of the authorization endpoint of the user service.
+    pub fn authz_url(&self) -> Url {
+        self.api_url()
+            .join("api/user/v1alpha1/authz")
+            .expect("Authorization URL must be valid")
+    }
+
+    /// Create a client, using this server as transport.
+    pub fn client(&self, token_provider: impl TokenProvider + 'static) -> DrogueClient {
+        DrogueClient::with_endpoints(
+            self.clone(),
+            self.api_url(),
+            self.endpoints(),
+            token_provider,
+        )
+    }
+
+    /// Add an access token for a user, which can be used for basic authentication.
+    ///
+    /// The returned value is the prefix of the token.
+    pub fn add_access_token<U, T>(&self, user: U, token: T) -> String
+    where
+        U: Into<String>,
+        T: Into<String>,
+    {
+        let mut state = self.state();
+        let prefix = format!("drg_{:04x}", state.next());
+        state.tokens.entry(user.into()).or_default().push(Token {
+            info: AccessToken {
+                created: Utc::now(),
+                prefix: prefix.clone(),
+                description: None,
+            },
+            token: token.into(),
+        });
+        prefix
+    }
+
+    /// Add an application, or replace an existing one.
+    ///
+    /// The server managed metadata is initialized, the application is owned by the default user.
+    pub fn add_app(&self, app: Application) {
+        let mut state = self.state();
+        let owner = state.default_user.clone();
+        state.insert_app(app, owner);
+    }
+
+    /// Add a device, or replace an existing one.
+    ///
+    /// The server managed metadata is initialized. The application of the device must exist,
+    /// otherwise the device is silently dropped.
+    pub fn add_device(&self, device: Device) {
+        let mut state = self.state();
+        if state.apps.contains_key(&device.metadata.application) {
+            state.insert_device(device);
+        }
+    }
+
+    /// Get the current state of an application.
+    pub fn app(&self, name: &str) -> Option<Application> {
+        self.state().apps.get(name).map(|entry| entry.app.clone())
+    }
+
+    /// Get the current state of a device.
+    pub fn device(&self, application: &str, name: &str) -> Option<Device> {
+        self.state()
+            .devices
+            .get(application)
+            .and_then(|devices| devices.get(name))
+            .cloned()
+    }
+
+    /// Get all commands, received so far.
+    pub fn commands(&self) -> Vec<MockCommand> {
+        self.state().commands.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // a panic while holding the lock doesn't leave the state inconsistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn handle(&self, request: TransportRequest) -> TransportResponse {
+        let mut state = self.state();
+        let request = Request::new(&request);
+
+        let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+        let result = match segments.as_slice() {
+            [".well-known", "drogue-endpoints"] => state.public_endpoints(&request),
+            [".well-known", "drogue-version"] => state.version(&request),
+            ["api", "console", "v1alpha1", "info"] => state.authenticated_endpoints(&request),
+            ["api", "registry", "v1alpha1", "apps", path @ ..] => state.registry(&request, path),
+            ["api", "command", "v1alpha1", "apps", app, "devices", device] => {
+                state.command(&request, app, device)
+            }
+            ["api", "admin", "v1alpha1", "apps", app, operation] => {
+                state.admin(&request, app, operation)
+            }
+            ["api", "tokens", "v1alpha1"] => state.tokens(&request),
+            ["api", "tokens", "v1alpha1", prefix] => state.token(&request, prefix),
+            ["api", "user", "v1alpha1", "authn"] => state.authn(&request),
+            ["api", "user", "v1alpha1", "authz"] => state.authz(&request),
+            _ => Err(error(StatusCode::NOT_FOUND, "NotFound", "Unknown endpoint")),
+        };
+
+        result.unwrap_or_else(Into::into)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl Transport for MockServer {
+    async fn execute(&self, request: TransportRequest) -> Result<TransportResponse, ClientError> {
+        Ok(self.handle(request))
+    }
+}
+
+/// The result of handling a request.
+type Reply = Result<TransportResponse, Failure>;
+
+/// A request which failed, reported with an error response.
+struct Failure {
+    status: StatusCode,
+    error: &'static str,
+    message: String,
+}
+
+impl From<Failure> for TransportResponse {
+    fn from(failure: Failure) -> Self {
+        json(
+            failure.status,
+            &ErrorInformation {
+                error: failure.error.to_string(),
+                message: failure.message,
+            },
+        )
+    }
+}
+
+/// A request, prepared for routing.
+struct Request<'r> {
+    method: &'r Method,
+    segments: Vec<String>,
+    query: HashMap<String, String>,
+    headers: &'r HeaderMap,
+    body: &'r [u8],
+}
+
+impl<'r> Request<'r> {
+    fn new(request: &'r TransportRequest) -> Self {
+        let segments = request
+            .url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            // trailing slashes, like used when creating devices, don't change the route
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+            .collect();
+
+        Self {
+            method: &request.method,
+            segments,
+            query: request.url.query_pairs().into_owned().collect(),
+            headers: &request.headers,
+            body: request.body.as_deref().unwrap_or_default(),
+        }
+    }
+
+    fn json<T: DeserializeOwned>(&self) -> Result<T, Failure> {
+        serde_json::from_slice(self.body).map_err(|err| {
+            error(
+                StatusCode::BAD_REQUEST,
+                "InvalidFormat",
+                format!("Failed to decode request payload: {}", err),
+            )
+        })
+    }
+
+    fn query<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, Failure> {
+        self.query
+            .get(name)
+            .map(|value| value.parse())
+            .transpose()
+            .map_err(|_| {
+                error(
+                    StatusCode::BAD_REQUEST,
+                    "InvalidRequest",
+                    format!("Invalid value for query parameter '{}'", name),
+                )
+            })
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE)?.to_str().ok()
+    }
+}
+
+impl State {
+    /// Get the next value of the counter, used for generating unique values.
+    fn next(&mut self) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+
+    /// Authenticate the request, returning the user the request is performed for.
+    fn user(&self, request: &Request) -> Result<String, Failure> {
+        let unauthorized = || error(StatusCode::UNAUTHORIZED, "Unauthorized", "Unauthorized");
+
+        let authorization = match request.headers.get(AUTHORIZATION) {
+            Some(value) => value.to_str().map_err(|_| unauthorized())?,
+            None if self.authentication => return Err(unauthorized()),
+            None => return Ok(self.default_user.clone()),
+        };
+
+        if authorization.starts_with("Bearer ") {
+            return Ok(self.default_user.clone());
+        }
+
+        let (user, token) = authorization
+            .strip_prefix("Basic ")
+            .and_then(|value| base64::decode(value.trim()).ok())
+            .and_then(|value| String::from_utf8(value).ok())
+            .and_then(|value| {
+                value
+                    .split_once(':')
+                    .map(|(user, token)| (user.to_string(), token.to_string()))
+            })
+            .ok_or_else(unauthorized)?;
+
+        match self.is_valid_token(&user, &token) {
+            true => Ok(user),
+            false => Err(unauthorized()),
+        }
+    }
+
+    fn is_valid_token(&self, user: &str, token: &str) -> bool {
+        self.tokens
+            .get(user)
+            .map(|tokens| tokens.iter().any(|t| t.token == token))
+            .unwrap_or_default()
+    }
+
+    /// Get an application, checking the user has the requested permission.
+    ///
+    /// Users without any access to the application, can't see it at all.
+    fn app_entry(
+        &self,
+        name: &str,
+        user: &str,
+        permission: Permission,
+    ) -> Result<&AppEntry, Failure> {
+        let not_found = || {
+            error(
+                StatusCode::NOT_FOUND,
+                "NotFound",
+                format!("Application '{}' not found", name),
+            )
+        };
+
+        let entry = self.apps.get(name).ok_or_else(not_found)?;
+        match entry.granted(user) {
+            Some(granted) if allows(granted, permission) => Ok(entry),
+            Some(_) => Err(error(
+                StatusCode::FORBIDDEN,
+                "NotAuthorized",
+                format!(
+                    "Missing permission '{}' on application '{}'",
+                    permission, name
+                ),
+            )),
+            None => Err(not_found()),
+        }
+    }
+
+    fn insert_app(&mut self, mut app: Application, owner: String) {
+        let version = self.next();
+        let meta = &mut app.metadata;
+        meta.uid = uid(version);
+        meta.creation_timestamp = Utc::now();
+        meta.generation = 1;
+        meta.resource_version = version.to_string();
+
+        self.devices.entry(meta.name.clone()).or_default();
+        self.apps.insert(
+            meta.name.clone(),
+            AppEntry {
+                app,
+                owner,
+                members: Members {
+                    resource_version: None,
+                    members: Default::default(),
+                },
+                transfer: None,
+            },
+        );
+    }
+
+    fn insert_device(&mut self, mut device: Device) {
+        let version = self.next();
+        let meta = &mut device.metadata;
+        meta.uid = uid(version);
+        meta.creation_timestamp = Utc::now();
+        meta.generation = 1;
+        meta.resource_version = version.to_string();
+
+        self.devices
+            .entry(meta.application.clone())
+            .or_default()
+            .insert(meta.name.clone(), device);
+    }
+
+    fn public_endpoints(&self, request: &Request) -> Reply {
+        expect_method(request, &Method::GET)?;
+        // like Drogue Cloud, this includes the endpoints for devices and integrations
+        Ok(json(
+            StatusCode::OK,
+            &Endpoints {
+                coap: self.endpoints.coap.clone(),
+                http: self.endpoints.http.clone(),
+                mqtt: self.endpoints.mqtt.clone(),
+                mqtt_integration: self.endpoints.mqtt_integration.clone(),
+                kafka_bootstrap_servers: self.endpoints.kafka_bootstrap_servers.clone(),
+                ..self.endpoints.publicize()
+            },
+        ))
+    }
+
+    fn authenticated_endpoints(&self, request: &Request) -> Reply {
+        expect_method(request, &Method::GET)?;
+        self.user(request)?;
+        Ok(json(StatusCode::OK, &self.endpoints))
+    }
+
+    fn version(&self, request: &Request) -> Reply {
+        expect_method(request, &Method::GET)?;
+        Ok(json(
+            StatusCode::OK,
+            &DrogueVersion {
+                version: self.version.clone(),
+            },
+        ))
+    }
+
+    fn command(&mut self, request: &Request, app: &str, device: &str) -> Reply {
+        expect_method(request, &Method::POST)?;
+        let user = self.user(request)?;
+        self.app_entry(app, &user, Permission::Write)?;
+
+        let command = request.query::<String>("command")?.ok_or_else(|| {
+            error(
+                StatusCode::BAD_REQUEST,
+                "InvalidRequest",
+                "Missing 'command' query parameter",
+            )
+        })?;
+
+        if !self.devices[app].contains_key(device) {
+            return Err(error(
+                StatusCode::NOT_FOUND,
+                "NotFound",
+                format!("Device '{}' not found", device),
+            ));
+        }
+
+        self.commands.push(MockCommand {
+            application: app.to_string(),
+            device: device.to_string(),
+            command,
+            payload: Some(request.body.to_vec()).filter(|body| !body.is_empty()),
+        });
+
+        Ok(empty(StatusCode::ACCEPTED))
+    }
+
+    fn admin(&mut self, request: &Request, app: &str, operation: &str) -> Reply {
+        let user = self.user(request)?;
+
+        match (request.method, operation) {
+            (&Method::GET, "members") => {
+                let entry = self.app_entry(app, &user, Permission::Admin)?;
+                Ok(json(StatusCode::OK, &entry.members()))
+            }
+            (&Method::PUT, "members") => {
+                let members: Members = request.json()?;
+                let current = self.app_entry(app, &user, Permission::Admin)?;
+                check_version(
+                    members.resource_version.as_deref(),
+                    &current.app.metadata.resource_version,
+                )?;
+
+                let version = self.next().to_string();
+                let entry = self.apps.get_mut(app).expect("Checked before");
+                entry.app.metadata.resource_version = version;
+                entry.members.members = members.members;
+
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (&Method::GET, "transfer-ownership") => {
+                let entry = self.app_entry(app, &user, Permission::Owner)?;
+                match &entry.transfer {
+                    Some(new_user) => Ok(json(
+                        StatusCode::OK,
+                        &TransferOwnership {
+                            new_user: new_user.clone(),
+                        },
+                    )),
+                    None => Err(error(
+                        StatusCode::NOT_FOUND,
+                        "NotFound",
+                        "No pending transfer",
+                    )),
+                }
+            }
+            (&Method::PUT, "transfer-ownership") => {
+                let transfer: TransferOwnership = request.json()?;
+                self.app_entry(app, &user, Permission::Owner)?;
+                self.apps.get_mut(app).expect("Checked before").transfer = Some(transfer.new_user);
+
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (&Method::DELETE, "transfer-ownership") => {
+                self.app_entry(app, &user, Permission::Owner)?;
+                match self
+                    .apps
+                    .get_mut(app)
+                    .expect("Checked before")
+                    .transfer
+                    .take()
+                {
+                    Some(_) => Ok(empty(StatusCode::NO_CONTENT)),
+                    None => Err(error(
+                        StatusCode::NOT_FOUND,
+                        "NotFound",
+                        "No pending transfer",
+                    )),
+                }
+            }
+            (&Method::PUT, "accept-ownership") => {
+                let entry = self
+                    .apps
+                    .get_mut(app)
+                    .filter(|entry| entry.transfer.is_some());
+                match entry {
+                    Some(entry) if entry.transfer.as_deref() == Some(user.as_str()) => {
+                        entry.transfer = None;
+                        entry.owner = user;
+                        Ok(empty(StatusCode::NO_CONTENT))
+                    }
+                    Some(_) => Err(error(
+                        StatusCode::FORBIDDEN,
+                        "NotAuthorized",
+                        "The transfer is not addressed to this user",
+                    )),
+                    None => Err(error(
+                        StatusCode::NOT_FOUND,
+                        "NotFound",
+                        "No pending transfer",
+                    )),
+                }
+            }
+            (_, "members" | "transfer-ownership" | "accept-ownership") => Err(method_not_allowed()),
+            _ => Err(error(StatusCode::NOT_FOUND, "NotFound", "Unknown endpoint")),
+        }
+    }
+
+    fn tokens(&mut self, request: &Request) -> Reply {
+        let user = self.user(request)?;
+
+        match *request.method {
+            Method::GET => {
+                let tokens: Vec<_> = self
+                    .tokens
+                    .get(&user)
+                    .into_iter()
+                    .flatten()
+                    .map(|token| token.info.clone())
+                    .collect();
+                Ok(json(StatusCode::OK, &tokens))
+            }
+            Method::POST => {
+                let prefix = format!("drg_{:04x}", self.next());
+                let token = format!("{}_{:016x}", prefix, random());
+
+                self.tokens.entry(user).or_default().push(Token {
+                    info: AccessToken {
+                        created: Utc::now(),
+                        prefix: prefix.clone(),
+                        description: request.query.get("description").cloned(),
+                    },
+                    token: token.clone(),
+                });
+
+                Ok(json(StatusCode::OK, &CreatedAccessToken { token, prefix }))
+            }
+            _ => Err(method_not_allowed()),
+        }
+    }
+
+    fn token(&mut self, request: &Request, prefix: &str) -> Reply {
+        expect_method(request, &Method::DELETE)?;
+        let user = self.user(request)?;
+
+        let tokens = self.tokens.entry(user).or_default();
+        let len = tokens.len();
+        tokens.retain(|token| token.info.prefix != prefix);
+
+        match tokens.len() < len {
+            true => Ok(empty(StatusCode::NO_CONTENT)),
+            false => Err(error(
+                StatusCode::NOT_FOUND,
+                "NotFound",
+                format!("Token '{}' not found", prefix),
+            )),
+        }
+    }
+
+    fn authn(&self, request: &Request) -> Reply {
+        expect_method(request, &Method::POST)?;
+        let request: AuthenticationRequest = request.json()?;
+
+        let outcome = match self.is_valid_token(&request.user_id, &request.access_token) {
+            true => AuthnOutcome::Known(UserDetails {
+                user_id: request.user_id,
+                roles: vec![],
+            }),
+            false => AuthnOutcome::Unknown,
+        };
+
+        Ok(json(StatusCode::OK, &AuthenticationResponse { outcome }))
+    }
+
+    fn authz(&self, request: &Request) -> Reply {
+        expect_method(request, &Method::POST)?;
+        let request: AuthorizationRequest = request.json()?;
+
+        let granted = request.user_id.as_deref().and_then(|user| {
+            self.apps
+                .get(&request.application)
+                .and_then(|entry| entry.granted(user))
+        });
+
+        let outcome = match granted {
+            Some(granted) if allows(granted, request.permission) => AuthzOutcome::Allow,
+            _ => AuthzOutcome::Deny,
+        };
+
+        Ok(json(StatusCode::OK, &AuthorizationResponse { outcome }))
+    }
+}
+
+impl AppEntry {
+    /// Get the highest permission granted to a user.
+    fn granted(&self, user: &str) -> Option<Permission> {
+        if self.owner == user {
+            return Some(Permission::Owner);
+        }
+
+        self.members
+            .members
+            .get(user)
+            .map(|entry| match entry.role {
+                Role::Admin => Permission::Admin,
+                Role::Manager => Permission::Write,
+                Role::Reader => Permission::Read,
+            })
+    }
+
+    fn members(&self) -> Members {
+        Members {
+            resource_version: Some(self.app.metadata.resource_version.clone()),
+            members: self.members.members.clone(),
+        }
+    }
+}
+
+/// Check if a granted permission includes the requested one.
+fn allows(granted: Permission, requested: Permission) -> bool {
+    fn level(permission: Permission) -> u8 {
+        match permission {
+            Permission::Owner => 3,
+            Permission::Admin => 2,
+            Permission::Write => 1,
+            Permission::Read => 0,
+        }
+    }
+
+    level(granted) >= level(requested)
+}
+
+/// Check the expected resource version, if one was provided.
+fn check_version(expected: Option<&str>, current: &str) -> Result<(), Failure> {
+    match expected {
+        Some(expected) if !expected.is_empty() && expected != current => Err(error(
+            StatusCode::CONFLICT,
+            "Conflict",
+            "The resource was modified in the meantime",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn expect_method(request: &Request, method: &Method) -> Result<(), Failure> {
+    match request.method == method {
+        true => Ok(()),
+        false => Err(method_not_allowed()),
+    }
+}
+
+fn default_endpoints(api_url: &Url) -> Endpoints {
+    let api = api_url.as_str().trim_end_matches('/').to_string();
+    let host = api_url.host_str().unwrap_or("localhost").to_string();
+    Endpoints {
+        api: Some(api.clone()),
+        console: Some(api.clone()),
+        http: Some(HttpEndpoint {
+            url: format!("{}/http", api),
+        }),
+        mqtt: Some(MqttEndpoint {
+            host: host.clone(),
+            port: 8883,
+        }),
+        mqtt_integration: Some(MqttEndpoint {
+            host: host.clone(),
+            port: 8884,
+        }),
+        kafka_bootstrap_servers: Some(format!("{}:9092", host)),
+        sso: Some(format!("{}/sso", api)),
+        issuer_url: Some(format!("{}/sso/realms/drogue", api)),
+        registry: Some(RegistryEndpoint { url: api.clone() }),
+        command_url: Some(api),
+        ..Default::default()
+    }
+}
+
+/// Create a unique ID, in the format of a UUID.
+fn uid(value: u64) -> String {
+    format!("00000000-0000-4000-8000-{:012x}", value)
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn empty(status: StatusCode) -> TransportResponse {
+    TransportResponse {
+        status,
+        headers: HeaderMap::new(),
+        body: vec![],
+    }
+}
+
+fn json<T: Serialize + ?Sized>(status: StatusCode, payload: &T) -> TransportResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    TransportResponse {
+        status,
+        headers,
+        body: serde_json::to_vec(payload).expect("Mock payloads must serialize"),
+    }
+}
+
+fn error<M: Into<String>>(status: StatusCode, error: &'static str, message: M) -> Failure {
+    Failure {
+        status,
+        error,
+        message: message.into(),
+    }
+}
+
+fn method_not_allowed() -> Failure {
+    error(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "MethodNotAllowed",
+        "Method not allowed",
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        admin::v1::MemberEntry,
+        openid::{AccessTokenProvider, NoTokenProvider},
+        user,
+    };
+
+    #[tokio::test]
+    async fn test_discovery() -> anyhow::Result<()> {
+        let server = MockServer::new().with_version("1.2.3");
+        let client = server.client(NoTokenProvider).discovery()?;
+
+        let version = client.get_drogue_cloud_version().await?.unwrap();
+        assert_eq!(version.version, "1.2.3");
+
+        let endpoints = client.get_public_endpoints().await?.unwrap();
+        assert!(endpoints.sso.is_some());
+        assert!(endpoints.http.is_some());
+        assert!(endpoints.command_url.is_none());
+
+        let endpoints = client.get_authenticated_endpoints().await?.unwrap();
+        assert_eq!(endpoints, server.endpoints());
+
+        let client = DrogueClient::discover(server.clone(), server.api_url(), NoTokenProvider)
+            .await?
+            .registry()?;
+        assert!(client.list_apps(None).await?.unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authentication() -> anyhow::Result<()> {
+        let server = MockServer::new().require_authentication(true);
+        server.add_access_token("alice", "secret");
+
+        let err = server
+            .client(NoTokenProvider)
+            .registry()?
+            .list_apps(None)
+            .await
+            .unwrap_err();
+        assert!(err.is_unauthorized());
+
+        let provider = AccessTokenProvider {
+            user: "alice".into(),
+            token: "wrong".into(),
+        };
+        let err = server
+            .client(provider)
+            .tokens()?
+            .get_tokens()
+            .await
+            .unwrap_err();
+        assert!(err.is_unauthorized());
+
+        let provider = AccessTokenProvider {
+            user: "alice".into(),
+            token: "secret".into(),
+        };
+        let tokens = server.client(provider).tokens()?;
+        let created = tokens.create_token(Some("test")).await?.unwrap();
+        assert!(created.token.starts_with(&created.prefix));
+
+        let list = tokens.get_tokens().await?.unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].description.as_deref(), Some("test"));
+
+        let client = user::v1::Client::new(
+            server.clone(),
+            server.authn_url(),
+            server.authz_url(),
+            NoTokenProvider,
+        );
+        let response = client
+            .authenticate_access_token(AuthenticationRequest {
+                user_id: "alice".into(),
+                access_token: created.token,
+            })
+            .await?;
+        assert!(matches!(response.outcome, AuthnOutcome::Known(_)));
+
+        assert!(tokens.delete_token(&created.prefix).await?);
+        assert!(!tokens.delete_token(&created.prefix).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        server.add_access_token("bob", "secret");
+        let admin = server.client(NoTokenProvider).admin()?;
+
+        let mut members = admin.get_members("app").await?.unwrap();
+        members
+            .members
+            .insert("bob".into(), MemberEntry { role: Role::Reader });
+        assert!(admin.update_members("app", members.clone()).await?);
+
+        // outdated resource version
+        let err = admin.update_members("app", members).await.unwrap_err();
+        assert!(err.is_conflict());
+
+        let authz = |user: &str, permission| AuthorizationRequest {
+            application: "app".into(),
+            permission,
+            user_id: Some(user.into()),
+            roles: vec![],
+        };
+        let client = user::v1::Client::new(
+            server.clone(),
+            server.authn_url(),
+            server.authz_url(),
+            NoTokenProvider,
+        );
+        let response = client.authorize(authz("bob", Permission::Read)).await?;
+        assert_eq!(response.outcome, AuthzOutcome::Allow);
+        let response = client.authorize(authz("bob", Permission::Write)).await?;
+        assert_eq!(response.outcome, AuthzOutcome::Deny);
+
+        // bob can't write as a reader
+        let bob = server.client(AccessTokenProvider {
+            user: "bob".into(),
+            token: "secret".into(),
+        });
+        let err = bob
+            .registry()?
+            .create_device(&Device::new("app", "device"))
+            .await
+            .unwrap_err();
+        assert!(err.is_forbidden());
+
+        assert!(admin.initiate_app_transfer("app", "bob").await?);
+        assert_eq!(
+            admin.read_app_transfer("app").await?.unwrap().new_user,
+            "bob"
+        );
+        assert!(bob.admin()?.accept_app_transfer("app").await?);
+        assert_eq!(
+            client
+                .authorize(authz("bob", Permission::Owner))
+                .await?
+                .outcome,
+            AuthzOutcome::Allow
+        );
+
+        // the previous owner lost access
+        assert!(admin.get_members("app").await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_command() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        server.add_device(Device::new("app", "device"));
+        let client = server.client(NoTokenProvider).command()?;
+
+        client
+            .publish_command(
+                "app",
+                "device",
+                "set",
+                Some(serde_json::json!({"on": true})),
+            )
+            .await?;
+        assert_eq!(
+            server.commands(),
+            vec![MockCommand {
+                application: "app".into(),
+                device: "device".into(),
+                command: "set".into(),
+                payload: Some(br#"{"on":true}"#.to_vec()),
+            }]
+        );
+
+        let err = client
+            .publish_command("app", "unknown", "set", None::<()>)
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,490 @@
+use super::{
+    check_version, empty, error, json, method_not_allowed, Failure, Reply, Request, State,
+};
+use crate::{
+    core::v1::PatchOperation,
+    registry::v1::{
+        labels::{LabelSelector, Operation},
+        Application, Device,
+    },
+    user::v1::authz::Permission,
+};
+use reqwest::{Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, convert::TryFrom};
+
+impl State {
+    pub(super) fn registry(&mut self, request: &Request, path: &[&str]) -> Reply {
+        let user = self.user(request)?;
+
+        match (request.method, path) {
+            (&Method::GET, []) => {
+                let selector = selector(request)?;
+                let apps: Vec<_> = self
+                    .apps
+                    .values()
+                    .filter(|entry| entry.granted(&user).is_some())
+                    .map(|entry| &entry.app)
+                    .filter(|app| matches(&selector, &app.metadata.labels))
+                    .collect();
+                paged(request, apps)
+            }
+            (&Method::POST, []) => {
+                let app: Application = request.json()?;
+                check_name(&app.metadata.name)?;
+                if self.apps.contains_key(&app.metadata.name) {
+                    return Err(already_exists(&app.metadata.name));
+                }
+
+                self.insert_app(app, user);
+                Ok(empty(StatusCode::CREATED))
+            }
+            (&Method::GET, [app]) => {
+                let entry = self.app_entry(app, &user, Permission::Read)?;
+                Ok(json(StatusCode::OK, &entry.app))
+            }
+            (&Method::PUT | &Method::PATCH, [app]) => {
+                let current = &self.app_entry(app, &user, Permission::Write)?.app;
+                let version = self.counter + 1;
+                let updated = modify(request, current, version)?;
+                if updated.metadata.name != *app {
+                    return Err(mismatch());
+                }
+
+                self.next();
+                self.apps.get_mut(*app).expect("Checked before").app = updated;
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (&Method::DELETE, [app]) => {
+                self.app_entry(app, &user, Permission::Owner)?;
+                self.apps.remove(*app);
+                self.devices.remove(*app);
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (&Method::GET, [app, "devices"]) => {
+                self.app_entry(app, &user, Permission::Read)?;
+                let selector = selector(request)?;
+                let devices: Vec<_> = self.devices[*app]
+                    .values()
+                    .filter(|device| matches(&selector, &device.metadata.labels))
+                    .collect();
+                paged(request, devices)
+            }
+            (&Method::POST, [app, "devices"]) => {
+                self.app_entry(app, &user, Permission::Write)?;
+                let device: Device = request.json()?;
+                check_name(&device.metadata.name)?;
+                if device.metadata.application != *app {
+                    return Err(mismatch());
+                }
+                if self.devices[*app].contains_key(&device.metadata.name) {
+                    return Err(already_exists(&device.metadata.name));
+                }
+
+                self.insert_device(device);
+                Ok(empty(StatusCode::CREATED))
+            }
+            (&Method::GET, [app, "devices", device]) => {
+                self.app_entry(app, &user, Permission::Read)?;
+                let device = self.devices[*app]
+                    .get(*device)
+                    .ok_or_else(|| device_not_found(device))?;
+                Ok(json(StatusCode::OK, device))
+            }
+            (&Method::PUT | &Method::PATCH, [app, "devices", device]) => {
+                self.app_entry(app, &user, Permission::Write)?;
+                let current = self.devices[*app]
+                    .get(*device)
+                    .ok_or_else(|| device_not_found(device))?;
+                let version = self.counter + 1;
+                let updated = modify(request, current, version)?;
+                if updated.metadata.application != *app || updated.metadata.name != *device {
+                    return Err(mismatch());
+                }
+
+                self.next();
+                self.devices
+                    .get_mut(*app)
+                    .expect("Checked before")
+                    .insert(device.to_string(), updated);
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (&Method::DELETE, [app, "devices", device]) => {
+                self.app_entry(app, &user, Permission::Write)?;
+                self.devices
+                    .get_mut(*app)
+                    .and_then(|devices| devices.remove(*device))
+                    .ok_or_else(|| device_not_found(device))?;
+                Ok(empty(StatusCode::NO_CONTENT))
+            }
+            (_, [] | [_] | [_, "devices"] | [_, "devices", _]) => Err(method_not_allowed()),
+            _ => Err(error(StatusCode::NOT_FOUND, "NotFound", "Unknown endpoint")),
+        }
+    }
+}
+
+/// A resource of the registry.
+trait Resource: Serialize + DeserializeOwned {
+    fn resource_version(&self) -> &str;
+
+    /// Take over the server managed metadata from the current state of the resource.
+    fn updated(self, current: &Self, version: u64) -> Self;
+}
+
+macro_rules! resource {
+    ($t:ty) => {
+        impl Resource for $t {
+            fn resource_version(&self) -> &str {
+                &self.metadata.resource_version
+            }
+
+            fn updated(mut self, current: &Self, version: u64) -> Self {
+                let changed = self.spec != current.spec
+                    || self.metadata.labels != current.metadata.labels
+                    || self.metadata.annotations != current.metadata.annotations
+                    || self.metadata.finalizers != current.metadata.finalizers;
+
+                self.metadata.uid = current.metadata.uid.clone();
+                self.metadata.creation_timestamp = current.metadata.creation_timestamp;
+                self.metadata.deletion_timestamp = current.metadata.deletion_timestamp;
+                self.metadata.generation = current.metadata.generation + changed as u64;
+                self.metadata.resource_version = version.to_string();
+                self
+            }
+        }
+    };
+}
+
+resource!(Application);
+resource!(Device);
+
+/// Apply an update (`PUT`) or patch (`PATCH`) request to a resource.
+fn modify<T: Resource>(request: &Request, current: &T, version: u64) -> Result<T, Failure> {
+    let updated: T = match *request.method {
+        Method::PATCH => {
+            let mut value = serde_json::to_value(current).map_err(internal_error)?;
+            match request.content_type() {
+                Some("application/json-patch+json") => {
+                    let operations: Vec<PatchOperation> = request.json()?;
+                    apply_json_patch(&mut value, operations).map_err(invalid_patch)?;
+                }
+                Some("application/merge-patch+json") | Some("application/json") => {
+                    apply_merge_patch(&mut value, &request.json()?);
+                }
+                _ => {
+                    return Err(error(
+                        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                        "UnsupportedMediaType",
+                        "Unsupported patch content type",
+                    ))
+                }
+            }
+            serde_json::from_value(value).map_err(invalid_patch)?
+        }
+        _ => {
+            let updated: T = request.json()?;
+            check_version(Some(updated.resource_version()), current.resource_version())?;
+            updated
+        }
+    };
+
+    Ok(updated.updated(current, version))
+}
+
+/// Apply a JSON merge patch ([RFC 7386]).
+///
+/// [RFC 7386]: https://datatracker.ietf.org/doc/html/rfc7386
+fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match value {
+                    Value::Null => {
+                        target.remove(key);
+                    }
+                    value => apply_merge_patch(target.entry(key).or_insert(Value::Null), value),
+                }
+            }
+        }
+        (target, Value::Object(_)) => {
+            *target = Value::Object(Map::new());
+            apply_merge_patch(target, patch);
+        }
+        (target, patch) => *target = patch.clone(),
+    }
+}
+
+/// Apply a JSON patch ([RFC 6902]).
+///
+/// [RFC 6902]: https://datatracker.ietf.org/doc/html/rfc6902
+fn apply_json_patch(target: &mut Value, operations: Vec<PatchOperation>) -> Result<(), String> {
+    for operation in operations {
+        match operation {
+            PatchOperation::Add { path, value } => add(target, &path, value)?,
+            PatchOperation::Remove { path } => {
+                remove(target, &path)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                remove(target, &path)?;
+                add(target, &path, value)?;
+            }
+            PatchOperation::Move { from, path } => {
+                let value = remove(target, &from)?;
+                add(target, &path, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = target
+                    .pointer(&from)
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value at '{}'", from))?;
+                add(target, &path, value)?;
+            }
+            PatchOperation::Test { path, value } => {
+                if target.pointer(&path) != Some(&value) {
+                    return Err(format!("Test failed for '{}'", path));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Split a JSON pointer into the pointer of the parent, and the unescaped key.
+fn split_pointer(path: &str) -> Result<(&str, String), String> {
+    let (parent, key) = path
+        .rsplit_once('/')
+        .ok_or_else(|| format!("Invalid path '{}'", path))?;
+    Ok((parent, key.replace("~1", "/").replace("~0", "~")))
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    if path.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+
+    let (parent, key) = split_pointer(path)?;
+    match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(key, value);
+        }
+        Some(Value::Array(array)) if key == "-" => array.push(value),
+        Some(Value::Array(array)) => match key.parse::<usize>() {
+            Ok(index) if index <= array.len() => array.insert(index, value),
+            _ => return Err(format!("Invalid array index '{}'", path)),
+        },
+        _ => return Err(format!("Missing parent of '{}'", path)),
+    }
+
+    Ok(())
+}
+
+fn remove(target: &mut Value, path: &str) -> Result<Value, String> {
+    let (parent, key) = split_pointer(path)?;
+    let value = match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&key),
+        Some(Value::Array(array)) => match key.parse::<usize>() {
+            Ok(index) if index < array.len() => Some(array.remove(index)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    value.ok_or_else(|| format!("Missing value at '{}'", path))
+}
+
+fn selector(request: &Request) -> Result<Option<LabelSelector>, Failure> {
+    request
+        .query
+        .get("labels")
+        .filter(|labels| !labels.is_empty())
+        .map(|labels| LabelSelector::try_from(labels.as_str()))
+        .transpose()
+        .map_err(|err| {
+            error(
+                StatusCode::BAD_REQUEST,
+                "InvalidRequest",
+                format!("Invalid label selector: {}", err),
+            )
+        })
+}
+
+/// Check if the labels match the selector.
+fn matches(selector: &Option<LabelSelector>, labels: &HashMap<String, String>) -> bool {
+    let selector = match selector {
+        Some(selector) => selector,
+        None => return true,
+    };
+
+    selector.0.iter().all(|operation| match operation {
+        Operation::Eq(key, value) => labels.get(key) == Some(value),
+        Operation::NotEq(key, value) => labels.get(key) != Some(value),
+        Operation::In(key, values) => matches!(labels.get(key), Some(v) if values.contains(v)),
+        Operation::NotIn(key, values) => !matches!(labels.get(key), Some(v) if values.contains(v)),
+        Operation::Exists(key) => labels.contains_key(key),
+        Operation::NotExists(key) => !labels.contains_key(key),
+    })
+}
+
+/// Reply with a page of a list, selected by the `offset` and `limit` query parameters.
+fn paged<T: Serialize>(request: &Request, items: Vec<T>) -> Reply {
+    let offset = request.query::<usize>("offset")?.unwrap_or_default();
+    let limit = request.query::<usize>("limit")?.unwrap_or(usize::MAX);
+
+    let page: Vec<_> = items.into_iter().skip(offset).take(limit).collect();
+    Ok(json(StatusCode::OK, &page))
+}
+
+fn check_name(name: &str) -> Result<(), Failure> {
+    match name.is_empty() {
+        true => Err(error(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "Missing resource name",
+        )),
+        false => Ok(()),
+    }
+}
+
+fn already_exists(name: &str) -> Failure {
+    error(
+        StatusCode::CONFLICT,
+        "AlreadyExists",
+        format!("Resource '{}' already exists", name),
+    )
+}
+
+fn device_not_found(name: &str) -> Failure {
+    error(
+        StatusCode::NOT_FOUND,
+        "NotFound",
+        format!("Device '{}' not found", name),
+    )
+}
+
+fn mismatch() -> Failure {
+    error(
+        StatusCode::BAD_REQUEST,
+        "InvalidRequest",
+        "The resource name doesn't match the request path",
+    )
+}
+
+fn invalid_patch<E: ToString>(err: E) -> Failure {
+    error(StatusCode::BAD_REQUEST, "InvalidPatch", err.to_string())
+}
+
+fn internal_error<E: ToString>(err: E) -> Failure {
+    error(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Internal",
+        err.to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{core::v1::Patch, mock::MockServer, openid::NoTokenProvider};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_apps() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        let client = server.client(NoTokenProvider).registry()?;
+
+        client.create_app(&Application::new("app")).await?;
+        let err = client
+            .create_app(&Application::new("app"))
+            .await
+            .unwrap_err();
+        assert!(err.is_conflict());
+
+        let mut app = client.get_app("app").await?.unwrap();
+        assert_eq!(app.metadata.generation, 1);
+        assert!(!app.metadata.uid.is_empty());
+
+        app.metadata.labels.insert("foo".into(), "bar".into());
+        assert!(client.update_app(&app).await?);
+        let current = client.get_app("app").await?.unwrap();
+        assert_eq!(current.metadata.generation, 2);
+        assert_eq!(current.metadata.uid, app.metadata.uid);
+
+        // outdated resource version
+        let err = client.update_app(&app).await.unwrap_err();
+        assert!(err.is_conflict());
+
+        assert!(!client.update_app(&Application::new("missing")).await?);
+        assert!(client.delete_app("app").await?);
+        assert!(!client.delete_app("app").await?);
+        assert!(client.get_app("app").await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_devices() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        let client = server.client(NoTokenProvider).registry()?;
+
+        let err = client
+            .create_device(&Device::new("missing", "device"))
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+
+        for (name, group) in [("a", "1"), ("b", "2"), ("c", "1"), ("d", "1")] {
+            let mut device = Device::new("app", name);
+            device.metadata.labels.insert("group".into(), group.into());
+            client.create_device(&device).await?;
+        }
+
+        let devices = client
+            .list_devices("app", Some(LabelSelector::try_from("group=1").unwrap()))
+            .await?
+            .unwrap();
+        let names: Vec<_> = devices.iter().map(|d| d.metadata.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "c", "d"]);
+
+        let devices = client
+            .list_devices_with_options(
+                "app",
+                &crate::registry::v1::ListOptions {
+                    labels: Some(LabelSelector::try_from("group notin (2)").unwrap()),
+                    limit: Some(1),
+                    offset: Some(1),
+                },
+            )
+            .await?
+            .unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].metadata.name, "c");
+
+        let patch = Patch::Merge(json!({"spec": {"foo": {"bar": 1}}}));
+        assert!(client.patch_device("app", "a", &patch).await?);
+        let patch = Patch::Json(vec![
+            PatchOperation::Test {
+                path: "/spec/foo/bar".into(),
+                value: json!(1),
+            },
+            PatchOperation::Replace {
+                path: "/spec/foo/bar".into(),
+                value: json!(2),
+            },
+        ]);
+        assert!(client.patch_device("app", "a", &patch).await?);
+        assert!(!client.patch_device("app", "x", &patch).await?);
+
+        let device = server.device("app", "a").unwrap();
+        assert_eq!(device.spec["foo"], json!({"bar": 2}));
+        assert_eq!(device.metadata.generation, 3);
+
+        assert!(client.delete_device("app", "a").await?);
+        assert!(!client.delete_device("app", "a").await?);
+
+        Ok(())
+    }
+}
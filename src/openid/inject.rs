@@ -1,8 +1,10 @@
 use crate::{
+    core::TransportRequest,
     error::ClientError,
     openid::{Credentials, TokenProvider},
 };
 use async_trait::async_trait;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
 use tracing::instrument;
 
 /// Allows injecting tokens.
@@ -30,3 +32,33 @@ impl TokenInjector for reqwest::RequestBuilder {
         }
     }
 }
+
+/// Injects tokens into a transport request by setting the authorization header.
+#[async_trait]
+impl TokenInjector for TransportRequest {
+    #[instrument(level = "debug", skip_all, err)]
+    async fn inject_token(
+        mut self,
+        token_provider: &dyn TokenProvider,
+    ) -> Result<Self, ClientError> {
+        if let Some(credentials) = token_provider
+            .provide_access_token()
+            .await
+            .map_err(|err| ClientError::Token(Box::new(err)))?
+        {
+            let value = match credentials {
+                Credentials::Bearer(token) => format!("Bearer {}", token),
+                Credentials::Basic(username, password) => format!(
+                    "Basic {}",
+                    base64::encode(format!("{}:{}", username, password.unwrap_or_default()))
+                ),
+            };
+            let mut value = HeaderValue::from_str(&value)
+                .map_err(|err| ClientError::Request(err.to_string()))?;
+            value.set_sensitive(true);
+            self.headers.insert(AUTHORIZATION, value);
+        }
+
+        Ok(self)
+    }
+}
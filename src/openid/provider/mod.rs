@@ -8,7 +8,7 @@ pub use self::openid::*;
 
 use crate::error::ClientError;
 use async_trait::async_trait;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 #[derive(Clone, Debug)]
 pub enum Credentials {
@@ -52,3 +52,13 @@ impl TokenProvider for String {
         Ok(Some(Credentials::Bearer(self.clone())))
     }
 }
+
+#[async_trait]
+impl<T> TokenProvider for Arc<T>
+where
+    T: TokenProvider + ?Sized,
+{
+    async fn provide_access_token(&self) -> Result<Option<Credentials>, ClientError> {
+        self.as_ref().provide_access_token().await
+    }
+}
@@ -0,0 +1,210 @@
+use super::{Device, DeviceSpecAliases};
+use crate::Translator;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The result of looking up a device by alias.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum AliasLookup {
+    /// No device has the alias.
+    NotFound,
+    /// Exactly one device has the alias.
+    Found(Device),
+    /// More than one device has the alias, so it can't be resolved unambiguously.
+    Ambiguous(Vec<Device>),
+}
+
+impl AliasLookup {
+    /// Get the device, if the alias could be resolved unambiguously.
+    pub fn found(self) -> Option<Device> {
+        match self {
+            Self::Found(device) => Some(device),
+            Self::NotFound | Self::Ambiguous(_) => None,
+        }
+    }
+}
+
+/// Find aliases which are used by more than one device.
+///
+/// Returns a map of colliding aliases, to the names of the devices using them. Aliases which are
+/// used by a single device only, are not part of the result.
+pub fn alias_collisions(devices: &[Device]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut aliases = BTreeMap::<_, BTreeSet<_>>::new();
+
+    for device in devices {
+        for alias in device_aliases(device) {
+            aliases
+                .entry(alias)
+                .or_default()
+                .insert(device.metadata.name.clone());
+        }
+    }
+
+    aliases.retain(|_, devices| devices.len() > 1);
+    aliases
+}
+
+/// Check if a device has an alias.
+pub fn has_alias(device: &Device, alias: &str) -> bool {
+    device_aliases(device).iter().any(|a| a == alias)
+}
+
+/// Get the aliases configured for a device.
+fn device_aliases(device: &Device) -> Vec<String> {
+    device
+        .section::<DeviceSpecAliases>()
+        .and_then(|s| s.ok())
+        .map(|s| s.0)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "reqwest")]
+mod client {
+    use super::*;
+    use crate::{error::ClientError, registry::v1::Client};
+    use futures::{future, TryStreamExt};
+    use std::fmt::Debug;
+    use tracing::instrument;
+
+    /// The page size used when scanning the devices of an application.
+    const SCAN_PAGE_SIZE: usize = 100;
+
+    impl Client {
+        /// Get a device by one of its aliases.
+        ///
+        /// The registry API has no filter for aliases, so the devices of the application are
+        /// listed page by page, and matched on the client side.
+        #[instrument]
+        pub async fn get_device_by_alias<A, S>(
+            &self,
+            application: A,
+            alias: S,
+        ) -> Result<AliasLookup, ClientError>
+        where
+            A: AsRef<str> + Debug,
+            S: AsRef<str> + Debug,
+        {
+            let alias = alias.as_ref();
+
+            let mut devices: Vec<Device> = self
+                .list_devices_stream(application, None, SCAN_PAGE_SIZE)
+                .try_filter(|device| future::ready(has_alias(device, alias)))
+                .try_collect()
+                .await?;
+
+            Ok(match devices.len() {
+                0 => AliasLookup::NotFound,
+                1 => AliasLookup::Found(devices.remove(0)),
+                _ => AliasLookup::Ambiguous(devices),
+            })
+        }
+
+        /// Find aliases which are used by more than one device of an application.
+        ///
+        /// See [`alias_collisions`] for details on the result.
+        #[instrument]
+        pub async fn find_alias_collisions<A>(
+            &self,
+            application: A,
+        ) -> Result<BTreeMap<String, BTreeSet<String>>, ClientError>
+        where
+            A: AsRef<str> + Debug,
+        {
+            let devices: Vec<Device> = self
+                .list_devices_stream(application, None, SCAN_PAGE_SIZE)
+                .try_collect()
+                .await?;
+
+            Ok(alias_collisions(&devices))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        mock::MockServer,
+        openid::NoTokenProvider,
+        registry::v1::{Application, Client},
+    };
+
+    fn device(name: &str, aliases: &[&str]) -> Device {
+        let mut device = Device::new("app", name);
+        device
+            .set_section(DeviceSpecAliases(
+                aliases.iter().map(|s| s.to_string()).collect(),
+            ))
+            .unwrap();
+        device
+    }
+
+    #[test]
+    fn test_has_alias() {
+        let device = device("foo", &["bar", "baz"]);
+        assert!(has_alias(&device, "bar"));
+        assert!(!has_alias(&device, "foo"));
+        assert!(!has_alias(&Device::new("app", "foo"), "bar"));
+    }
+
+    #[test]
+    fn test_alias_collisions() {
+        let collisions = alias_collisions(&[
+            device("a", &["x", "y", "y"]),
+            device("b", &["y", "z"]),
+            device("c", &["z"]),
+            device("d", &[]),
+        ]);
+
+        assert_eq!(
+            collisions
+                .iter()
+                .map(|(alias, devices)| (
+                    alias.as_str(),
+                    devices.iter().map(|d| d.as_str()).collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("y", vec!["a", "b"]), ("z", vec!["b", "c"])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_device_by_alias() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        for i in 0..250 {
+            server.add_device(device(&format!("device-{}", i), &[&format!("alias-{}", i)]));
+        }
+        server.add_device(device("a", &["shared"]));
+        server.add_device(device("b", &["shared"]));
+
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider);
+
+        let found = client
+            .get_device_by_alias("app", "alias-242")
+            .await?
+            .found()
+            .unwrap();
+        assert_eq!(found.metadata.name, "device-242");
+
+        assert_eq!(
+            client.get_device_by_alias("app", "unknown").await?,
+            AliasLookup::NotFound
+        );
+
+        match client.get_device_by_alias("app", "shared").await? {
+            AliasLookup::Ambiguous(devices) => {
+                assert_eq!(
+                    devices
+                        .iter()
+                        .map(|d| d.metadata.name.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["a", "b"]
+                );
+            }
+            lookup => panic!("Unexpected result: {:?}", lookup),
+        }
+
+        Ok(())
+    }
+}
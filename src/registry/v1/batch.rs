@@ -0,0 +1,301 @@
+use crate::error::ClientError;
+
+/// The outcome of a single item of a batch operation.
+#[derive(Debug)]
+pub enum BatchOutcome {
+    /// The resource was created.
+    Created,
+    /// The resource was not created, as it already exists.
+    AlreadyExists,
+    /// The resource was updated.
+    Updated,
+    /// The resource was deleted.
+    Deleted,
+    /// The resource to update or delete could not be found.
+    NotFound,
+    /// The operation failed.
+    Failed(ClientError),
+}
+
+impl BatchOutcome {
+    /// Check if the operation failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+}
+
+/// The result of a single item of a batch operation.
+#[derive(Debug)]
+pub struct BatchItem {
+    /// The name of the resource.
+    pub name: String,
+    /// The outcome of the operation.
+    pub outcome: BatchOutcome,
+}
+
+/// The report of a batch operation, containing the outcome of each item.
+///
+/// The items are reported in the same order as they were provided.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub items: Vec<BatchItem>,
+}
+
+impl BatchReport {
+    /// Check if all items were processed without failure.
+    pub fn is_success(&self) -> bool {
+        !self.items.iter().any(|item| item.outcome.is_failed())
+    }
+
+    /// The items which failed.
+    pub fn failed(&self) -> impl Iterator<Item = (&str, &ClientError)> {
+        self.items.iter().filter_map(|item| match &item.outcome {
+            BatchOutcome::Failed(err) => Some((item.name.as_str(), err)),
+            _ => None,
+        })
+    }
+
+    /// Count the items, matching an outcome.
+    pub fn count<F>(&self, f: F) -> usize
+    where
+        F: Fn(&BatchOutcome) -> bool,
+    {
+        self.items.iter().filter(|item| f(&item.outcome)).count()
+    }
+}
+
+impl Extend<BatchItem> for BatchReport {
+    fn extend<T: IntoIterator<Item = BatchItem>>(&mut self, iter: T) {
+        self.items.extend(iter)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+mod client {
+    use super::*;
+    use crate::registry::v1::{Client, Device};
+    use futures::{stream, StreamExt};
+    use std::fmt::Debug;
+    use tracing::instrument;
+
+    impl Client {
+        /// Create a batch of devices.
+        ///
+        /// The devices are created concurrently, limited by the concurrency of the client. A
+        /// failure does not stop the processing of the remaining devices, but is reported as
+        /// part of the result.
+        #[instrument(skip(devices))]
+        pub async fn create_devices<I>(&self, devices: I) -> BatchReport
+        where
+            I: IntoIterator<Item = Device>,
+        {
+            stream::iter(devices)
+                .map(|device| async move {
+                    let outcome = match self.create_device(&device).await {
+                        Ok(_) => BatchOutcome::Created,
+                        Err(err) if err.is_conflict() => BatchOutcome::AlreadyExists,
+                        Err(err) => BatchOutcome::Failed(err),
+                    };
+                    BatchItem {
+                        name: device.metadata.name,
+                        outcome,
+                    }
+                })
+                .buffered(self.concurrency())
+                .collect()
+                .await
+        }
+
+        /// Update (overwrite) a batch of devices.
+        ///
+        /// The devices are updated concurrently, limited by the concurrency of the client. A
+        /// failure does not stop the processing of the remaining devices, but is reported as
+        /// part of the result.
+        #[instrument(skip(devices))]
+        pub async fn update_devices<I>(&self, devices: I) -> BatchReport
+        where
+            I: IntoIterator<Item = Device>,
+        {
+            stream::iter(devices)
+                .map(|device| async move {
+                    let outcome = match self.update_device(&device).await {
+                        Ok(true) => BatchOutcome::Updated,
+                        Ok(false) => BatchOutcome::NotFound,
+                        Err(err) => BatchOutcome::Failed(err),
+                    };
+                    BatchItem {
+                        name: device.metadata.name,
+                        outcome,
+                    }
+                })
+                .buffered(self.concurrency())
+                .collect()
+                .await
+        }
+
+        /// Delete a batch of devices of an application.
+        ///
+        /// The devices are deleted concurrently, limited by the concurrency of the client. A
+        /// failure does not stop the processing of the remaining devices, but is reported as
+        /// part of the result.
+        #[instrument(skip(devices))]
+        pub async fn delete_devices<A, I, D>(&self, application: A, devices: I) -> BatchReport
+        where
+            A: AsRef<str> + Debug,
+            I: IntoIterator<Item = D>,
+            D: AsRef<str>,
+        {
+            let application = application.as_ref();
+
+            stream::iter(devices)
+                .map(|device| async move {
+                    let outcome = match self.delete_device(application, device.as_ref()).await {
+                        Ok(true) => BatchOutcome::Deleted,
+                        Ok(false) => BatchOutcome::NotFound,
+                        Err(err) => BatchOutcome::Failed(err),
+                    };
+                    BatchItem {
+                        name: device.as_ref().to_string(),
+                        outcome,
+                    }
+                })
+                .buffered(self.concurrency())
+                .collect()
+                .await
+        }
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod test {
+    use super::*;
+    use crate::{
+        core::{Transport, TransportRequest, TransportResponse},
+        openid::NoTokenProvider,
+        registry::v1::{Client, Device},
+    };
+    use async_trait::async_trait;
+    use reqwest::{header::HeaderMap, Method, StatusCode};
+    use url::Url;
+
+    /// A fake registry, knowing device "exists", and failing for device "fail".
+    #[derive(Debug)]
+    struct FakeRegistry;
+
+    #[async_trait]
+    impl Transport for FakeRegistry {
+        async fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> Result<TransportResponse, ClientError> {
+            let name = match request.method {
+                Method::POST => request
+                    .body
+                    .as_deref()
+                    .map(serde_json::from_slice::<Device>)
+                    .transpose()?
+                    .map(|device| device.metadata.name)
+                    .unwrap_or_default(),
+                _ => request
+                    .url
+                    .path_segments()
+                    .and_then(|mut s| s.next_back())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+
+            let status = match (request.method, name.as_str()) {
+                (_, "fail") => StatusCode::INTERNAL_SERVER_ERROR,
+                (Method::POST, "exists") => StatusCode::CONFLICT,
+                (Method::POST, _) => StatusCode::CREATED,
+                (_, "exists") => StatusCode::NO_CONTENT,
+                _ => StatusCode::NOT_FOUND,
+            };
+
+            Ok(TransportResponse {
+                status,
+                headers: HeaderMap::new(),
+                body: vec![],
+            })
+        }
+    }
+
+    fn client() -> Client {
+        Client::new(
+            FakeRegistry,
+            Url::parse("http://localhost").unwrap(),
+            NoTokenProvider,
+        )
+        .with_concurrency(2)
+    }
+
+    fn outcomes(report: &BatchReport) -> Vec<(&str, &'static str)> {
+        report
+            .items
+            .iter()
+            .map(|item| {
+                let outcome = match item.outcome {
+                    BatchOutcome::Created => "created",
+                    BatchOutcome::AlreadyExists => "exists",
+                    BatchOutcome::Updated => "updated",
+                    BatchOutcome::Deleted => "deleted",
+                    BatchOutcome::NotFound => "not-found",
+                    BatchOutcome::Failed(_) => "failed",
+                };
+                (item.name.as_str(), outcome)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_create_devices() {
+        let report = client()
+            .create_devices(
+                ["new", "exists", "fail", "other"]
+                    .into_iter()
+                    .map(|name| Device::new("app", name)),
+            )
+            .await;
+
+        assert_eq!(
+            outcomes(&report),
+            vec![
+                ("new", "created"),
+                ("exists", "exists"),
+                ("fail", "failed"),
+                ("other", "created")
+            ]
+        );
+        assert!(!report.is_success());
+        assert_eq!(report.failed().count(), 1);
+        assert_eq!(report.count(|o| matches!(o, BatchOutcome::Created)), 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_devices() {
+        let report = client()
+            .update_devices(
+                ["exists", "missing"]
+                    .into_iter()
+                    .map(|name| Device::new("app", name)),
+            )
+            .await;
+        assert_eq!(
+            outcomes(&report),
+            vec![("exists", "updated"), ("missing", "not-found")]
+        );
+        assert!(report.is_success());
+
+        let report = client()
+            .delete_devices("app", ["exists", "missing", "fail"])
+            .await;
+        assert_eq!(
+            outcomes(&report),
+            vec![
+                ("exists", "deleted"),
+                ("missing", "not-found"),
+                ("fail", "failed")
+            ]
+        );
+    }
+}
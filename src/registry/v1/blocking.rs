@@ -0,0 +1,401 @@
+//! A blocking variant of the registry client.
+
+use super::{
+    AliasLookup, Application, BatchReport, Bundle, Device, DevicesLookup, GatewayGraph,
+    ImportOptions, ImportPlan, ImportReport, ListOptions,
+};
+use crate::{
+    core::{v1::Patch, BlockingRuntime, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+    registry::v1::labels::LabelSelector,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    time::Duration,
+};
+use url::Url;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A blocking device registry client.
+///
+/// This wraps the async [`Client`](super::Client), and runs its operations on a
+/// [`BlockingRuntime`]. The operations must not be called from within an async context.
+///
+/// Streaming operations, like watching resources, are only available on the async client.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client,
+    runtime: BlockingRuntime,
+}
+
+impl Client {
+    /// Create a new client instance, using its own runtime.
+    pub fn new(
+        transport: impl Transport + 'static,
+        registry_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        Self::from_async(super::Client::new(transport, registry_url, token_provider))
+    }
+
+    /// Wrap an async client, using its own runtime.
+    pub fn from_async(client: super::Client) -> ClientResult<Self> {
+        Ok(Self::with_runtime(client, BlockingRuntime::new()?))
+    }
+
+    /// Wrap an async client, using an existing runtime.
+    pub fn with_runtime(client: super::Client, runtime: BlockingRuntime) -> Self {
+        Self {
+            inner: client,
+            runtime,
+        }
+    }
+
+    /// The wrapped async client.
+    pub fn inner(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// Set the maximum number of concurrent requests, used when fetching multiple resources.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.inner = self.inner.with_concurrency(concurrency);
+        self
+    }
+
+    /// Set a timeout for requests made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.with_timeout(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Blocking variant of [`super::Client::list_apps`].
+    pub fn list_apps(
+        &self,
+        labels: Option<LabelSelector>,
+    ) -> ClientResult<Option<Vec<Application>>> {
+        self.runtime.block_on(self.inner.list_apps(labels))
+    }
+
+    /// Blocking variant of [`super::Client::list_apps_with_options`].
+    pub fn list_apps_with_options(
+        &self,
+        options: &ListOptions,
+    ) -> ClientResult<Option<Vec<Application>>> {
+        self.runtime
+            .block_on(self.inner.list_apps_with_options(options))
+    }
+
+    /// Blocking variant of [`super::Client::get_app`].
+    pub fn get_app<A>(&self, application: A) -> ClientResult<Option<Application>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.get_app(application))
+    }
+
+    /// Blocking variant of [`super::Client::get_device`].
+    pub fn get_device<A, D>(&self, application: A, device: D) -> ClientResult<Option<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.get_device(application, device))
+    }
+
+    /// Blocking variant of [`super::Client::get_devices`].
+    pub fn get_devices<A, D>(&self, application: A, devices: &[D]) -> ClientResult<Vec<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.get_devices(application, devices))
+    }
+
+    /// Blocking variant of [`super::Client::get_devices_with_missing`].
+    pub fn get_devices_with_missing<A, D>(
+        &self,
+        application: A,
+        devices: &[D],
+    ) -> ClientResult<DevicesLookup>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.get_devices_with_missing(application, devices))
+    }
+
+    /// Blocking variant of [`super::Client::get_device_and_gateways`].
+    pub fn get_device_and_gateways<A, D>(
+        &self,
+        application: A,
+        device: D,
+    ) -> ClientResult<Option<(Device, Vec<Device>)>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.get_device_and_gateways(application, device))
+    }
+
+    /// Blocking variant of [`super::Client::list_devices`].
+    pub fn list_devices<A>(
+        &self,
+        application: A,
+        labels: Option<LabelSelector>,
+    ) -> ClientResult<Option<Vec<Device>>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.list_devices(application, labels))
+    }
+
+    /// Blocking variant of [`super::Client::list_devices_with_options`].
+    pub fn list_devices_with_options<A>(
+        &self,
+        application: A,
+        options: &ListOptions,
+    ) -> ClientResult<Option<Vec<Device>>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.list_devices_with_options(application, options))
+    }
+
+    /// Blocking variant of [`super::Client::update_app`].
+    pub fn update_app(&self, application: &Application) -> ClientResult<bool> {
+        self.runtime.block_on(self.inner.update_app(application))
+    }
+
+    /// Blocking variant of [`super::Client::update_device`].
+    pub fn update_device(&self, device: &Device) -> ClientResult<bool> {
+        self.runtime.block_on(self.inner.update_device(device))
+    }
+
+    /// Blocking variant of [`super::Client::patch_app`].
+    pub fn patch_app<A>(&self, application: A, patch: &Patch) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.patch_app(application, patch))
+    }
+
+    /// Blocking variant of [`super::Client::patch_device`].
+    pub fn patch_device<A, D>(&self, application: A, device: D, patch: &Patch) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.patch_device(application, device, patch))
+    }
+
+    /// Blocking variant of [`super::Client::update_app_with`].
+    pub fn update_app_with<A, F>(
+        &self,
+        application: A,
+        mutator: F,
+    ) -> ClientResult<Option<Application>>
+    where
+        A: AsRef<str> + Debug,
+        F: FnMut(&mut Application),
+    {
+        self.runtime
+            .block_on(self.inner.update_app_with(application, mutator))
+    }
+
+    /// Blocking variant of [`super::Client::update_device_with`].
+    pub fn update_device_with<A, D, F>(
+        &self,
+        application: A,
+        device: D,
+        mutator: F,
+    ) -> ClientResult<Option<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+        F: FnMut(&mut Device),
+    {
+        self.runtime
+            .block_on(self.inner.update_device_with(application, device, mutator))
+    }
+
+    /// Blocking variant of [`super::Client::create_app`].
+    pub fn create_app(&self, app: &Application) -> ClientResult<Option<()>> {
+        self.runtime.block_on(self.inner.create_app(app))
+    }
+
+    /// Blocking variant of [`super::Client::delete_app`].
+    pub fn delete_app<A>(&self, application: A) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.delete_app(application))
+    }
+
+    /// Blocking variant of [`super::Client::create_device`].
+    pub fn create_device(&self, device: &Device) -> ClientResult<Option<()>> {
+        self.runtime.block_on(self.inner.create_device(device))
+    }
+
+    /// Blocking variant of [`super::Client::delete_device`].
+    pub fn delete_device<A, D>(&self, application: A, device: D) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.delete_device(application, device))
+    }
+
+    /// Blocking variant of [`super::Client::get_device_by_alias`].
+    pub fn get_device_by_alias<A, S>(&self, application: A, alias: S) -> ClientResult<AliasLookup>
+    where
+        A: AsRef<str> + Debug,
+        S: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.get_device_by_alias(application, alias))
+    }
+
+    /// Blocking variant of [`super::Client::find_alias_collisions`].
+    pub fn find_alias_collisions<A>(
+        &self,
+        application: A,
+    ) -> ClientResult<BTreeMap<String, BTreeSet<String>>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.find_alias_collisions(application))
+    }
+
+    /// Blocking variant of [`super::Client::create_devices`].
+    pub fn create_devices<I>(&self, devices: I) -> BatchReport
+    where
+        I: IntoIterator<Item = Device>,
+    {
+        self.runtime.block_on(self.inner.create_devices(devices))
+    }
+
+    /// Blocking variant of [`super::Client::update_devices`].
+    pub fn update_devices<I>(&self, devices: I) -> BatchReport
+    where
+        I: IntoIterator<Item = Device>,
+    {
+        self.runtime.block_on(self.inner.update_devices(devices))
+    }
+
+    /// Blocking variant of [`super::Client::delete_devices`].
+    pub fn delete_devices<A, I, D>(&self, application: A, devices: I) -> BatchReport
+    where
+        A: AsRef<str> + Debug,
+        I: IntoIterator<Item = D>,
+        D: AsRef<str>,
+    {
+        self.runtime
+            .block_on(self.inner.delete_devices(application, devices))
+    }
+
+    /// Blocking variant of [`super::Client::export_bundle`].
+    pub fn export_bundle<A>(&self, application: A) -> ClientResult<Option<Bundle>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.export_bundle(application))
+    }
+
+    /// Blocking variant of [`super::Client::plan_import`].
+    pub fn plan_import(&self, bundle: &Bundle, prune: bool) -> ClientResult<ImportPlan> {
+        self.runtime.block_on(self.inner.plan_import(bundle, prune))
+    }
+
+    /// Blocking variant of [`super::Client::import_bundle`].
+    pub fn import_bundle(
+        &self,
+        bundle: &Bundle,
+        options: &ImportOptions,
+    ) -> ClientResult<ImportReport> {
+        self.runtime
+            .block_on(self.inner.import_bundle(bundle, options))
+    }
+
+    /// Blocking variant of [`super::Client::resolve_gateway_graph`].
+    pub fn resolve_gateway_graph<A, D>(
+        &self,
+        application: A,
+        device: D,
+        max_depth: usize,
+    ) -> ClientResult<Option<GatewayGraph>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(
+            self.inner
+                .resolve_gateway_graph(application, device, max_depth),
+        )
+    }
+
+    /// Blocking variant of [`super::Client::devices_using_gateway`].
+    pub fn devices_using_gateway<A, G>(
+        &self,
+        application: A,
+        gateway: G,
+    ) -> ClientResult<Option<Vec<Device>>>
+    where
+        A: AsRef<str> + Debug,
+        G: AsRef<str> + Debug,
+    {
+        self.runtime
+            .block_on(self.inner.devices_using_gateway(application, gateway))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{mock::MockServer, openid::NoTokenProvider};
+
+    #[test]
+    fn test_blocking() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider)?;
+
+        client.create_app(&Application::new("app"))?;
+        client.create_device(&Device::new("app", "device"))?;
+
+        let device = client
+            .update_device_with("app", "device", |device| {
+                device.metadata.labels.insert("foo".into(), "bar".into());
+            })?
+            .unwrap();
+        assert_eq!(device.metadata.labels["foo"], "bar");
+        assert_eq!(
+            client.get_device("app", "device")?.unwrap().metadata.labels["foo"],
+            "bar"
+        );
+
+        assert!(client.delete_device("app", "device")?);
+        assert!(client.get_device("app", "device")?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,537 @@
+use super::{Application, BatchReport, Device};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+
+/// An application, together with all of its devices.
+///
+/// Bundles are intended to keep the configuration of an application outside of Drogue Cloud,
+/// e.g. in a git repository. They only contain the parts of the resources which are managed by
+/// the user: the name, labels, annotations and the `spec` section. Server managed metadata,
+/// like the creation timestamp or the resource version, is omitted when serializing a bundle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bundle {
+    #[serde(serialize_with = "serialize_resource")]
+    pub application: Application,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(serialize_with = "serialize_resources")]
+    pub devices: Vec<Device>,
+}
+
+impl Bundle {
+    /// Create a new bundle, removing all server managed information from the resources.
+    pub fn new(mut application: Application, mut devices: Vec<Device>) -> Self {
+        application.metadata.clean();
+        application.status.clear();
+
+        for device in &mut devices {
+            device.metadata.clean();
+            device.status.clear();
+        }
+
+        Self {
+            application,
+            devices,
+        }
+    }
+
+    /// Parse a bundle from JSON.
+    pub fn from_json(json: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(json)
+    }
+
+    /// Serialize the bundle to JSON.
+    pub fn to_json(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    /// Parse a bundle from YAML.
+    #[cfg(feature = "serde_yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    /// Serialize the bundle to YAML.
+    #[cfg(feature = "serde_yaml")]
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+/// The metadata fields, managed by the server.
+const SERVER_MANAGED: &[&str] = &[
+    "uid",
+    "creationTimestamp",
+    "generation",
+    "resourceVersion",
+    "deletionTimestamp",
+    "finalizers",
+];
+
+/// Convert a resource to a JSON value, without the server managed metadata.
+fn user_managed<T: Serialize>(resource: &T) -> Result<serde_json::Value, serde_json::Error> {
+    let mut value = serde_json::to_value(resource)?;
+    if let Some(metadata) = value
+        .get_mut("metadata")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        for field in SERVER_MANAGED {
+            metadata.remove(*field);
+        }
+    }
+    Ok(value)
+}
+
+fn serialize_resource<T, S>(resource: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    user_managed(resource)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+fn serialize_resources<T, S>(resources: &[T], serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    resources
+        .iter()
+        .map(user_managed)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+/// A change of the application, required to bring the registry in sync with a bundle.
+///
+/// Importing a bundle never deletes the application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApplicationChange {
+    /// The application must be created.
+    Create(Application),
+    /// The application must be updated, contains the updated application.
+    Update(Application),
+}
+
+/// A change of a device, required to bring the registry in sync with a bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<T> {
+    /// The resource must be created.
+    Create(T),
+    /// The resource must be updated, contains the updated resource.
+    Update(T),
+    /// The resource must be deleted, contains the current resource.
+    Delete(T),
+}
+
+/// The changes, required to bring the registry in sync with a bundle.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportPlan {
+    /// The change of the application, `None` if it is up to date.
+    pub application: Option<ApplicationChange>,
+    /// The changes of the devices, devices which are up to date are not included.
+    pub devices: Vec<Change<Device>>,
+}
+
+impl ImportPlan {
+    /// Compute the changes, by comparing the bundle with the current state of the registry.
+    ///
+    /// Resources are considered equal when their labels, annotations and `spec` sections are
+    /// equal. Updates are based on the current resource, so that server managed information,
+    /// like the resource version, is kept.
+    ///
+    /// If `prune` is `true`, devices which are not part of the bundle will be deleted.
+    pub fn new(
+        bundle: &Bundle,
+        current_application: Option<&Application>,
+        current_devices: &[Device],
+        prune: bool,
+    ) -> Self {
+        let name = &bundle.application.metadata.name;
+
+        let application = match current_application {
+            None => Some(ApplicationChange::Create(bundle.application.clone())),
+            Some(current) if !current.matches(&bundle.application) => Some(
+                ApplicationChange::Update(current.updated(&bundle.application)),
+            ),
+            Some(_) => None,
+        };
+
+        let current: HashMap<&str, &Device> = current_devices
+            .iter()
+            .map(|device| (device.metadata.name.as_str(), device))
+            .collect();
+
+        let mut devices = Vec::new();
+
+        for desired in &bundle.devices {
+            let mut desired = desired.clone();
+            desired.metadata.application = name.clone();
+
+            match current.get(desired.metadata.name.as_str()) {
+                None => devices.push(Change::Create(desired)),
+                Some(current) if !current.matches(&desired) => {
+                    devices.push(Change::Update(current.updated(&desired)))
+                }
+                Some(_) => {}
+            }
+        }
+
+        if prune {
+            let desired: HashSet<&str> = bundle
+                .devices
+                .iter()
+                .map(|device| device.metadata.name.as_str())
+                .collect();
+
+            for current in current_devices {
+                if !desired.contains(current.metadata.name.as_str()) {
+                    devices.push(Change::Delete(current.clone()));
+                }
+            }
+        }
+
+        Self {
+            application,
+            devices,
+        }
+    }
+
+    /// Check if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.application.is_none() && self.devices.is_empty()
+    }
+}
+
+/// Options for importing a bundle.
+#[derive(Clone, Debug, Default)]
+pub struct ImportOptions {
+    /// Only compute the plan, without applying it.
+    pub dry_run: bool,
+    /// Delete devices which are not part of the bundle.
+    pub prune: bool,
+}
+
+/// The result of importing a bundle.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// The plan, which was computed.
+    pub plan: ImportPlan,
+    /// The outcome of creating devices.
+    pub created: BatchReport,
+    /// The outcome of updating devices.
+    pub updated: BatchReport,
+    /// The outcome of deleting devices.
+    pub deleted: BatchReport,
+}
+
+impl ImportReport {
+    /// Check if all devices were processed without failure.
+    pub fn is_success(&self) -> bool {
+        self.created.is_success() && self.updated.is_success() && self.deleted.is_success()
+    }
+}
+
+/// The user managed content of a resource.
+trait Content: Clone {
+    /// Check if the user managed content of both resources is equal.
+    fn matches(&self, other: &Self) -> bool;
+    /// Create a copy, with the user managed content taken from `desired`.
+    fn updated(&self, desired: &Self) -> Self;
+}
+
+macro_rules! content {
+    ($t:ty) => {
+        impl Content for $t {
+            fn matches(&self, other: &Self) -> bool {
+                self.metadata.labels == other.metadata.labels
+                    && self.metadata.annotations == other.metadata.annotations
+                    && self.spec == other.spec
+            }
+
+            fn updated(&self, desired: &Self) -> Self {
+                let mut result = self.clone();
+                result.metadata.labels = desired.metadata.labels.clone();
+                result.metadata.annotations = desired.metadata.annotations.clone();
+                result.spec = desired.spec.clone();
+                result
+            }
+        }
+    };
+}
+
+content!(Application);
+content!(Device);
+
+/// Remove server managed information from metadata.
+trait Clean {
+    fn clean(&mut self);
+}
+
+macro_rules! clean {
+    ($t:ty) => {
+        impl Clean for $t {
+            fn clean(&mut self) {
+                self.uid = Default::default();
+                self.creation_timestamp = Default::default();
+                self.generation = Default::default();
+                self.resource_version = Default::default();
+                self.deletion_timestamp = Default::default();
+                self.finalizers = Default::default();
+            }
+        }
+    };
+}
+
+clean!(crate::meta::v1::NonScopedMetadata);
+clean!(crate::meta::v1::ScopedMetadata);
+
+#[cfg(feature = "reqwest")]
+mod client {
+    use super::*;
+    use crate::{error::ClientError, registry::v1::Client};
+    use futures::TryStreamExt;
+    use std::fmt::Debug;
+    use tracing::instrument;
+
+    /// The page size used when listing the devices of an application.
+    const PAGE_SIZE: usize = 100;
+
+    impl Client {
+        /// Export an application and all of its devices as a bundle.
+        ///
+        /// If the application does not exist, `None` is returned.
+        #[instrument]
+        pub async fn export_bundle<A>(&self, application: A) -> Result<Option<Bundle>, ClientError>
+        where
+            A: AsRef<str> + Debug,
+        {
+            let app = match self.get_app(application.as_ref()).await? {
+                Some(app) => app,
+                None => return Ok(None),
+            };
+
+            let devices = self
+                .list_devices_stream(application, None, PAGE_SIZE)
+                .try_collect()
+                .await?;
+
+            Ok(Some(Bundle::new(app, devices)))
+        }
+
+        /// Compute the changes, required to bring the registry in sync with a bundle.
+        ///
+        /// See [`ImportPlan::new`] for details.
+        #[instrument(skip(bundle))]
+        pub async fn plan_import(
+            &self,
+            bundle: &Bundle,
+            prune: bool,
+        ) -> Result<ImportPlan, ClientError> {
+            let name = &bundle.application.metadata.name;
+
+            let app = self.get_app(name).await?;
+            let devices: Vec<Device> = match app {
+                Some(_) => {
+                    self.list_devices_stream(name, None, PAGE_SIZE)
+                        .try_collect()
+                        .await?
+                }
+                None => vec![],
+            };
+
+            Ok(ImportPlan::new(bundle, app.as_ref(), &devices, prune))
+        }
+
+        /// Import a bundle, bringing the registry in sync with it.
+        ///
+        /// The application is created or updated first, an error doing so will fail the import.
+        /// Devices are processed as batch operations, failures are reported as part of the
+        /// result.
+        #[instrument(skip(bundle))]
+        pub async fn import_bundle(
+            &self,
+            bundle: &Bundle,
+            options: &ImportOptions,
+        ) -> Result<ImportReport, ClientError> {
+            let plan = self.plan_import(bundle, options.prune).await?;
+
+            if options.dry_run {
+                return Ok(ImportReport {
+                    plan,
+                    ..Default::default()
+                });
+            }
+
+            let name = &bundle.application.metadata.name;
+
+            let found = match &plan.application {
+                Some(ApplicationChange::Create(app)) => {
+                    self.create_app(app).await?;
+                    true
+                }
+                Some(ApplicationChange::Update(app)) => self.update_app(app).await?,
+                None => true,
+            };
+            if !found {
+                return Err(ClientError::UnexpectedResponse(format!(
+                    "application '{}' was deleted during import",
+                    name
+                )));
+            }
+
+            let mut create = Vec::new();
+            let mut update = Vec::new();
+            let mut delete = Vec::new();
+            for change in &plan.devices {
+                match change {
+                    Change::Create(device) => create.push(device.clone()),
+                    Change::Update(device) => update.push(device.clone()),
+                    Change::Delete(device) => delete.push(device.metadata.name.clone()),
+                }
+            }
+
+            Ok(ImportReport {
+                created: self.create_devices(create).await,
+                updated: self.update_devices(update).await,
+                deleted: self.delete_devices(name, delete).await,
+                plan,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn device(name: &str, spec: serde_json::Value) -> Device {
+        let mut device = Device::new("app", name);
+        device.spec = serde_json::from_value(spec).unwrap();
+        device
+    }
+
+    #[test]
+    fn test_bundle_cleans_metadata() {
+        let mut app = Application::new("app");
+        app.metadata.uid = "1234".into();
+        app.metadata.resource_version = "1".into();
+        app.status.insert("foo".into(), json!("bar"));
+
+        let bundle = Bundle::new(app, vec![device("foo", json!({}))]);
+
+        assert_eq!(
+            serde_json::to_value(&bundle).unwrap(),
+            json!({
+                "application": {
+                    "metadata": {
+                        "name": "app"
+                    }
+                },
+                "devices": [{
+                    "metadata": {
+                        "application": "app",
+                        "name": "foo"
+                    }
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_bundle_serializes_user_managed() {
+        let mut app = Application::new("app");
+        app.metadata.generation = 3;
+        app.metadata.resource_version = "1".into();
+        app.metadata.labels.insert("foo".into(), "bar".into());
+
+        let bundle = Bundle {
+            application: app,
+            devices: vec![],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&bundle).unwrap(),
+            json!({
+                "application": {
+                    "metadata": {
+                        "name": "app",
+                        "labels": {"foo": "bar"}
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_plan() {
+        let bundle = Bundle::new(
+            Application::new("app"),
+            vec![
+                device("same", json!({"foo": 1})),
+                device("changed", json!({"foo": 2})),
+                device("new", json!({})),
+            ],
+        );
+
+        let mut changed = device("changed", json!({"foo": 1}));
+        changed.metadata.resource_version = "42".into();
+
+        let current = vec![
+            device("same", json!({"foo": 1})),
+            changed,
+            device("removed", json!({})),
+        ];
+
+        let plan = ImportPlan::new(&bundle, Some(&Application::new("app")), &current, false);
+        assert_eq!(plan.application, None);
+        assert_eq!(plan.devices.len(), 2);
+        match &plan.devices[0] {
+            Change::Update(device) => {
+                assert_eq!(device.metadata.name, "changed");
+                assert_eq!(device.metadata.resource_version, "42");
+                assert_eq!(device.spec["foo"], json!(2));
+            }
+            change => panic!("unexpected change: {:?}", change),
+        }
+        assert!(matches!(&plan.devices[1], Change::Create(d) if d.metadata.name == "new"));
+
+        let plan = ImportPlan::new(&bundle, None, &current, true);
+        assert!(matches!(
+            plan.application,
+            Some(ApplicationChange::Create(_))
+        ));
+        assert!(
+            matches!(plan.devices.last(), Some(Change::Delete(d)) if d.metadata.name == "removed")
+        );
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn test_yaml() {
+        let bundle = Bundle::from_yaml(
+            r#"
+application:
+  metadata:
+    name: app
+devices:
+  - metadata:
+      application: app
+      name: foo
+    spec:
+      alias: ["bar"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(bundle.devices[0].spec["alias"], json!(["bar"]));
+        assert_eq!(
+            Bundle::from_yaml(&bundle.to_yaml().unwrap()).unwrap(),
+            bundle
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use super::{Application, Client, Device, DeviceSpecGatewaySelector};
+use crate::{error::ClientError, Translator};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::instrument;
+
+#[cfg(feature = "telemetry")]
+use crate::metrics::record_cache_lookup;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A cache, shared between clones of the client, holding optional resources.
+type SharedCache<K, V> = Arc<Mutex<Cache<K, Option<V>>>>;
+
+/// Options for the [`CachingClient`].
+#[derive(Clone, Debug)]
+pub struct CacheOptions {
+    /// The time an existing resource is kept in the cache.
+    pub ttl: Duration,
+    /// The time the absence of a resource is kept in the cache.
+    ///
+    /// If this is `None`, missing resources will not be cached.
+    pub negative_ttl: Option<Duration>,
+    /// The maximum number of entries, per type of resource.
+    pub max_entries: usize,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+            negative_ttl: Some(Duration::from_secs(10)),
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// A device registry client, caching the results of lookups.
+///
+/// Only the lookup of single applications and devices is cached. Listing resources, as well as
+/// all modifying operations, are not. The cache is not invalidated automatically when
+/// resources get modified, use the `invalidate_*` functions for this.
+///
+/// Cloning the client will share the cache between the clones. The client is not available on
+/// `wasm32`, as it relies on [`Instant`] for expiring entries.
+#[derive(Clone, Debug)]
+pub struct CachingClient {
+    client: Client,
+    options: CacheOptions,
+    apps: SharedCache<String, Application>,
+    devices: SharedCache<(String, String), Device>,
+}
+
+impl CachingClient {
+    /// Create a new caching client, wrapping an existing client.
+    pub fn new(client: Client, options: CacheOptions) -> Self {
+        Self {
+            client,
+            apps: Arc::new(Mutex::new(Cache::new(options.max_entries))),
+            devices: Arc::new(Mutex::new(Cache::new(options.max_entries))),
+            options,
+        }
+    }
+
+    /// Get access to the wrapped client, bypassing the cache.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Get an application by name, using the cache.
+    #[instrument]
+    pub async fn get_app<A>(&self, application: A) -> ClientResult<Option<Application>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        let key = application.as_ref().to_string();
+
+        if let Some(app) = lookup("application", &self.apps, &key) {
+            return Ok(app);
+        }
+
+        let app = self.client.get_app(&key).await?;
+        self.store(&self.apps, key, app.clone());
+
+        Ok(app)
+    }
+
+    /// Get a device by name, using the cache.
+    #[instrument]
+    pub async fn get_device<A, D>(&self, application: A, device: D) -> ClientResult<Option<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        let key = (
+            application.as_ref().to_string(),
+            device.as_ref().to_string(),
+        );
+
+        if let Some(device) = lookup("device", &self.devices, &key) {
+            return Ok(device);
+        }
+
+        let device = self.client.get_device(&key.0, &key.1).await?;
+        self.store(&self.devices, key, device.clone());
+
+        Ok(device)
+    }
+
+    /// Get a list of devices, using the cache.
+    ///
+    /// The devices are fetched concurrently, limited by the concurrency of the wrapped client.
+    /// The function will only return devices that could be found.
+    #[instrument]
+    pub async fn get_devices<A, D>(
+        &self,
+        application: A,
+        devices: &[D],
+    ) -> ClientResult<Vec<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        stream::iter(devices)
+            .map(|device| self.get_device(application.as_ref(), device))
+            .buffered(self.client.concurrency())
+            // filter out missing devices
+            .filter_map(|device| async { device.transpose() })
+            .try_collect()
+            .await
+    }
+
+    /// Get a device by name, resolving all first level gateways, using the cache.
+    #[instrument]
+    pub async fn get_device_and_gateways<A, D>(
+        &self,
+        application: A,
+        device: D,
+    ) -> ClientResult<Option<(Device, Vec<Device>)>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        let device = match self.get_device(application.as_ref(), device).await? {
+            Some(device) => device,
+            None => return Ok(None),
+        };
+
+        let gateways = match device
+            .section::<DeviceSpecGatewaySelector>()
+            .and_then(|s| s.ok())
+        {
+            Some(gw_sel) => self.get_devices(application, &gw_sel.match_names).await?,
+            None => vec![],
+        };
+
+        Ok(Some((device, gateways)))
+    }
+
+    /// Remove an application from the cache.
+    ///
+    /// This does not remove the devices of the application from the cache.
+    pub fn invalidate_app<A>(&self, application: A)
+    where
+        A: AsRef<str>,
+    {
+        self.apps
+            .lock()
+            .unwrap()
+            .remove(&application.as_ref().to_string());
+    }
+
+    /// Remove a device from the cache.
+    pub fn invalidate_device<A, D>(&self, application: A, device: D)
+    where
+        A: AsRef<str>,
+        D: AsRef<str>,
+    {
+        self.devices.lock().unwrap().remove(&(
+            application.as_ref().to_string(),
+            device.as_ref().to_string(),
+        ));
+    }
+
+    /// Remove all devices of an application from the cache.
+    pub fn invalidate_devices<A>(&self, application: A)
+    where
+        A: AsRef<str>,
+    {
+        let application = application.as_ref();
+        self.devices
+            .lock()
+            .unwrap()
+            .retain(|(app, _)| app != application);
+    }
+
+    /// Remove all entries from the cache.
+    pub fn clear(&self) {
+        self.apps.lock().unwrap().clear();
+        self.devices.lock().unwrap().clear();
+    }
+
+    fn store<K, V>(&self, cache: &Mutex<Cache<K, Option<V>>>, key: K, value: Option<V>)
+    where
+        K: Hash + Eq + Clone,
+    {
+        let ttl = match value {
+            Some(_) => Some(self.options.ttl),
+            None => self.options.negative_ttl,
+        };
+
+        if let Some(ttl) = ttl {
+            cache.lock().unwrap().insert(key, value, ttl);
+        }
+    }
+}
+
+fn lookup<K, V>(name: &str, cache: &Mutex<Cache<K, V>>, key: &K) -> Option<V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    let result = cache.lock().unwrap().get(key);
+
+    #[cfg(feature = "telemetry")]
+    record_cache_lookup(name, result.is_some());
+    #[cfg(not(feature = "telemetry"))]
+    let _ = name;
+
+    result
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    /// The time the entry expires, `None` if the TTL exceeds the range of [`Instant`].
+    expires: Option<Instant>,
+    /// The sequence number of the insertion, to match the entry against the queue.
+    seq: u64,
+}
+
+impl<V> Entry<V> {
+    fn expired(&self, now: Instant) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+}
+
+/// A size bound cache, with expiring entries.
+///
+/// When the cache is full, the oldest entry gets evicted, in the order of insertion. Expired
+/// entries are removed when they are looked up, or by a sweep which runs whenever the queue of
+/// insertions grows beyond twice the maximum number of entries.
+#[derive(Debug)]
+struct Cache<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// The insertions, oldest first. Items not matching the sequence number of their entry are
+    /// stale, and get skipped.
+    queue: VecDeque<(u64, K)>,
+    seq: u64,
+    max_entries: usize,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            queue: VecDeque::new(),
+            seq: 0,
+            max_entries,
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        match self.entries.get(key) {
+            Some(entry) if !entry.expired(Instant::now()) => Some(entry.value.clone()),
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&mut self, key: K, value: V, ttl: Duration) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let expires = now.checked_add(ttl);
+
+        // replacing an entry makes its previous queue item stale
+        self.entries.remove(&key);
+
+        while self.entries.len() >= self.max_entries {
+            match self.queue.pop_front() {
+                Some((seq, key)) => {
+                    if self.entries.get(&key).map(|entry| entry.seq) == Some(seq) {
+                        self.entries.remove(&key);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        self.seq += 1;
+        let seq = self.seq;
+        self.queue.push_back((seq, key.clone()));
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                expires,
+                seq,
+            },
+        );
+
+        if self.queue.len() > self.max_entries.saturating_mul(2) {
+            self.sweep(now);
+        }
+    }
+
+    /// Remove expired entries, and stale items of the queue.
+    fn sweep(&mut self, now: Instant) {
+        self.entries.retain(|_, entry| !entry.expired(now));
+        let entries = &self.entries;
+        self.queue
+            .retain(|(seq, key)| entries.get(key).map(|entry| entry.seq) == Some(*seq));
+    }
+
+    fn remove(&mut self, key: &K) {
+        self.entries.remove(key);
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: Fn(&K) -> bool,
+    {
+        self.entries.retain(|k, _| f(k));
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        core::{Transport, TransportRequest, TransportResponse},
+        openid::NoTokenProvider,
+    };
+    use async_trait::async_trait;
+    use reqwest::{header::HeaderMap, StatusCode};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use url::Url;
+
+    /// A fake registry, knowing only device "app/foo".
+    #[derive(Debug, Default)]
+    struct CountingRegistry {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Transport for CountingRegistry {
+        async fn execute(&self, request: TransportRequest) -> ClientResult<TransportResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let (status, body) = match request.url.path() {
+                "/api/registry/v1alpha1/apps/app/devices/foo" => (
+                    StatusCode::OK,
+                    serde_json::to_vec(&Device::new("app", "foo"))?,
+                ),
+                _ => (StatusCode::NOT_FOUND, vec![]),
+            };
+            Ok(TransportResponse {
+                status,
+                headers: HeaderMap::new(),
+                body,
+            })
+        }
+    }
+
+    fn client(options: CacheOptions) -> (CachingClient, Arc<CountingRegistry>) {
+        let registry = Arc::new(CountingRegistry::default());
+        let client = Client::new(
+            registry.clone(),
+            Url::parse("http://localhost").unwrap(),
+            NoTokenProvider,
+        );
+        (CachingClient::new(client, options), registry)
+    }
+
+    #[tokio::test]
+    async fn test_cache() -> anyhow::Result<()> {
+        let (client, registry) = client(Default::default());
+
+        assert!(client.get_device("app", "foo").await?.is_some());
+        assert!(client.get_device("app", "foo").await?.is_some());
+        assert_eq!(registry.calls.load(Ordering::SeqCst), 1);
+
+        // negative caching
+
+        assert!(client.get_device("app", "bar").await?.is_none());
+        assert!(client.get_device("app", "bar").await?.is_none());
+        assert_eq!(registry.calls.load(Ordering::SeqCst), 2);
+
+        // invalidation
+
+        client.invalidate_device("app", "foo");
+        assert!(client.get_device("app", "foo").await?.is_some());
+        assert_eq!(registry.calls.load(Ordering::SeqCst), 3);
+
+        client.invalidate_devices("app");
+        assert!(client.get_device("app", "foo").await?.is_some());
+        assert!(client.get_device("app", "bar").await?.is_none());
+        assert_eq!(registry.calls.load(Ordering::SeqCst), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_no_negative_caching() -> anyhow::Result<()> {
+        let (client, registry) = client(CacheOptions {
+            negative_ttl: None,
+            ..Default::default()
+        });
+
+        assert!(client.get_device("app", "bar").await?.is_none());
+        assert!(client.get_device("app", "bar").await?.is_none());
+        assert_eq!(registry.calls.load(Ordering::SeqCst), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_bounds() {
+        let mut cache = Cache::new(2);
+
+        cache.insert("a", 1, Duration::from_secs(60));
+        cache.insert("b", 2, Duration::from_secs(60));
+        cache.insert("a", 3, Duration::from_secs(60));
+        cache.insert("c", 4, Duration::from_secs(60));
+
+        assert_eq!(cache.get(&"a"), Some(3));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), Some(4));
+
+        // expired entries
+
+        cache.insert("d", 5, Duration::ZERO);
+        assert_eq!(cache.get(&"d"), None);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"c"), Some(4));
+    }
+
+    #[test]
+    fn test_cache_sweep() {
+        let mut cache = Cache::new(4);
+
+        cache.insert("expired", 0, Duration::ZERO);
+        cache.insert("forever", 1, Duration::MAX);
+
+        // re-inserting the same entry must not grow the queue without bounds
+        for i in 0..100 {
+            cache.insert("a", i, Duration::from_secs(60));
+        }
+
+        assert!(cache.queue.len() <= 8);
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.get(&"expired"), None);
+        assert_eq!(cache.get(&"forever"), Some(1));
+        assert_eq!(cache.get(&"a"), Some(99));
+    }
+}
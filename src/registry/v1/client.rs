@@ -1,47 +1,133 @@
 use super::data::*;
-use crate::core::CoreClient;
+use crate::core::{v1::Patch, CoreClient, RequestOptions, Transport};
 use crate::openid::TokenProvider;
 use crate::registry::v1::labels::LabelSelector;
 use crate::{error::ClientError, Translator};
-use futures::{stream, StreamExt, TryStreamExt};
-use std::{fmt::Debug, sync::Arc};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 use tracing::instrument;
 use url::Url;
 
 /// A device registry client
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     registry_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
+    concurrency: usize,
 }
 
 type ClientResult<T> = Result<T, ClientError>;
 
+/// The default number of concurrent requests, used when fetching multiple resources.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The number of attempts, the `update_*_with` functions perform in case of a conflict.
+pub const UPDATE_CONFLICT_ATTEMPTS: usize = 5;
+
+/// The result of looking up a list of devices.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DevicesLookup {
+    /// The devices which could be found.
+    pub found: Vec<Device>,
+    /// The names of the devices which could not be found.
+    pub missing: Vec<String>,
+}
+
+/// Options for listing resources.
+#[derive(Clone, Debug, Default)]
+pub struct ListOptions {
+    /// Label selectors to filter the list.
+    pub labels: Option<LabelSelector>,
+    /// The maximum number of items to return.
+    pub limit: Option<usize>,
+    /// The number of items to skip.
+    pub offset: Option<usize>,
+}
+
+impl ListOptions {
+    /// Convert the options into query parameters.
+    pub fn to_query_parameters(&self) -> Vec<(String, String)> {
+        let mut query = self
+            .labels
+            .as_ref()
+            .map(|l| l.to_query_parameters())
+            .unwrap_or_default();
+
+        if let Some(limit) = self.limit {
+            query.push(("limit".to_string(), limit.to_string()));
+        }
+        if let Some(offset) = self.offset {
+            query.push(("offset".to_string(), offset.to_string()));
+        }
+
+        query
+    }
+}
+
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "registry"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new client instance.
     pub fn new(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         registry_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             registry_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+            concurrency: DEFAULT_CONCURRENCY,
         }
     }
 
+    /// Set the maximum number of concurrent requests, used when fetching multiple resources.
+    ///
+    /// A value of zero will be treated as one.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The maximum number of concurrent requests, used when fetching multiple resources.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
+        }
+        client
+    }
+
     /// craft url for the registry
     fn url(&self, application: Option<&str>, device: Option<&str>) -> ClientResult<Url> {
         let mut url = self.registry_url.clone();
@@ -87,7 +173,42 @@ impl Client {
 
         let labels = labels.map(|l| l.to_query_parameters());
 
-        self.read_with_query_parameters(url, labels).await
+        self.read_with_query_parameters("list_apps", url, labels)
+            .await
+    }
+
+    /// List applications, using the provided options.
+    ///
+    /// This allows to fetch a single page of the list, using `limit` and `offset`.
+    #[instrument]
+    pub async fn list_apps_with_options(
+        &self,
+        options: &ListOptions,
+    ) -> ClientResult<Option<Vec<Application>>> {
+        self.read_with_query_parameters(
+            "list_apps",
+            self.url(None, None)?,
+            Some(options.to_query_parameters()),
+        )
+        .await
+    }
+
+    /// List all applications, fetching them lazily in pages of `page_size` items.
+    pub fn list_apps_stream(
+        &self,
+        labels: Option<LabelSelector>,
+        page_size: usize,
+    ) -> impl Stream<Item = ClientResult<Application>> {
+        let client = self.clone();
+        paged(
+            labels,
+            page_size,
+            |app: &Application| &app.metadata.name,
+            move |options| {
+                let client = client.clone();
+                async move { client.list_apps_with_options(&options).await }
+            },
+        )
     }
 
     /// Get an application by name.
@@ -102,7 +223,8 @@ impl Client {
     where
         A: AsRef<str> + Debug,
     {
-        self.read(self.url(Some(application.as_ref()), None)?).await
+        self.read("get_app", self.url(Some(application.as_ref()), None)?)
+            .await
     }
 
     /// Get a device by name.
@@ -118,12 +240,18 @@ impl Client {
         A: AsRef<str> + Debug,
         D: AsRef<str> + Debug,
     {
-        self.read(self.url(Some(application.as_ref()), Some(device.as_ref()))?)
-            .await
+        self.read(
+            "get_device",
+            self.url(Some(application.as_ref()), Some(device.as_ref()))?,
+        )
+        .await
     }
 
     /// Get a list of devices.
     ///
+    /// The devices are fetched concurrently, limited by the configured concurrency. The result
+    /// will be in the same order as the requested names.
+    ///
     /// The function will only return devices that could be found.
     #[instrument]
     pub async fn get_devices<A, D>(
@@ -136,7 +264,8 @@ impl Client {
         D: AsRef<str> + Debug,
     {
         stream::iter(devices)
-            .then(|device| self.get_device(application.as_ref(), device))
+            .map(|device| self.get_device(application.as_ref(), device))
+            .buffered(self.concurrency)
             // filter out missing devices
             .filter_map(|device| async { device.transpose() })
             // collect to a map
@@ -144,6 +273,38 @@ impl Client {
             .await
     }
 
+    /// Get a list of devices, reporting the names of devices which could not be found.
+    ///
+    /// The devices are fetched concurrently, limited by the configured concurrency. The found
+    /// devices, as well as the missing names, will be in the same order as the requested names.
+    #[instrument]
+    pub async fn get_devices_with_missing<A, D>(
+        &self,
+        application: A,
+        devices: &[D],
+    ) -> ClientResult<DevicesLookup>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        let application = application.as_ref();
+        stream::iter(devices)
+            .map(|name| async move {
+                self.get_device(application, name)
+                    .await
+                    .map(|device| device.ok_or_else(|| name.as_ref().to_string()))
+            })
+            .buffered(self.concurrency)
+            .try_fold(DevicesLookup::default(), |mut lookup, device| async move {
+                match device {
+                    Ok(device) => lookup.found.push(device),
+                    Err(name) => lookup.missing.push(name),
+                }
+                Ok(lookup)
+            })
+            .await
+    }
+
     /// Get a device by name, resolving all first level gateways.
     #[instrument]
     pub async fn get_device_and_gateways<A, D>(
@@ -156,7 +317,10 @@ impl Client {
         D: AsRef<str> + Debug,
     {
         let device: Option<Device> = self
-            .read(self.url(Some(application.as_ref()), Some(device.as_ref()))?)
+            .read(
+                "get_device_and_gateways",
+                self.url(Some(application.as_ref()), Some(device.as_ref()))?,
+            )
             .await?;
 
         if let Some(device) = device {
@@ -199,7 +363,58 @@ impl Client {
 
         let labels = labels.map(|l| l.to_query_parameters());
 
-        self.read_with_query_parameters(url, labels).await
+        self.read_with_query_parameters("list_devices", url, labels)
+            .await
+    }
+
+    /// List devices, using the provided options.
+    ///
+    /// This allows to fetch a single page of the list, using `limit` and `offset`.
+    #[instrument]
+    pub async fn list_devices_with_options<A>(
+        &self,
+        application: A,
+        options: &ListOptions,
+    ) -> ClientResult<Option<Vec<Device>>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.read_with_query_parameters(
+            "list_devices",
+            self.url(Some(application.as_ref()), Some(""))?,
+            Some(options.to_query_parameters()),
+        )
+        .await
+    }
+
+    /// List all devices, fetching them lazily in pages of `page_size` items.
+    ///
+    /// If the application does not exist, the stream will be empty.
+    pub fn list_devices_stream<A>(
+        &self,
+        application: A,
+        labels: Option<LabelSelector>,
+        page_size: usize,
+    ) -> impl Stream<Item = ClientResult<Device>>
+    where
+        A: AsRef<str> + Debug,
+    {
+        let client = self.clone();
+        let application = application.as_ref().to_string();
+        paged(
+            labels,
+            page_size,
+            |device: &Device| &device.metadata.name,
+            move |options| {
+                let client = client.clone();
+                let application = application.clone();
+                async move {
+                    client
+                        .list_devices_with_options(application, &options)
+                        .await
+                }
+            },
+        )
     }
 
     /// Update (overwrite) an application.
@@ -208,6 +423,7 @@ impl Client {
     #[instrument]
     pub async fn update_app(&self, application: &Application) -> ClientResult<bool> {
         self.update(
+            "update_app",
             self.url(Some(application.metadata.name.as_str()), None)?,
             Some(application),
         )
@@ -220,6 +436,7 @@ impl Client {
     #[instrument]
     pub async fn update_device(&self, device: &Device) -> ClientResult<bool> {
         self.update(
+            "update_device",
             self.url(
                 Some(device.metadata.application.as_str()),
                 Some(device.metadata.name.as_str()),
@@ -229,10 +446,147 @@ impl Client {
         .await
     }
 
+    /// Patch an application.
+    ///
+    /// The application must exist, otherwise `false` is returned.
+    #[instrument]
+    pub async fn patch_app<A>(&self, application: A, patch: &Patch) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+    {
+        self.patch(
+            "patch_app",
+            self.url(Some(application.as_ref()), None)?,
+            patch,
+        )
+        .await
+    }
+
+    /// Patch a device.
+    ///
+    /// A patch for a single section of the device can be created using e.g.
+    /// [`Patch::merge_section`]:
+    ///
+    /// ```rust,no_run
+    /// use drogue_client::{core::v1::Patch, registry::v1::{Client, DeviceSpecCore}};
+    ///
+    /// # async fn run(client: Client) -> anyhow::Result<()> {
+    /// let patch = Patch::merge_section(&DeviceSpecCore { disabled: true })?;
+    /// client.patch_device("app", "device", &patch).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// The application and device must exist, otherwise `false` is returned.
+    #[instrument]
+    pub async fn patch_device<A, D>(
+        &self,
+        application: A,
+        device: D,
+        patch: &Patch,
+    ) -> ClientResult<bool>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+    {
+        self.patch(
+            "patch_device",
+            self.url(Some(application.as_ref()), Some(device.as_ref()))?,
+            patch,
+        )
+        .await
+    }
+
+    /// Update an application, using optimistic locking.
+    ///
+    /// This will read the current state of the application, apply the mutation, and write it back
+    /// using the resource version it was read with. If the application was modified in the
+    /// meantime, the process will be repeated, up to [`UPDATE_CONFLICT_ATTEMPTS`] times.
+    ///
+    /// If the application does not exist, `None` is returned. Otherwise the updated application is
+    /// returned.
+    #[instrument(skip(mutator))]
+    pub async fn update_app_with<A, F>(
+        &self,
+        application: A,
+        mut mutator: F,
+    ) -> ClientResult<Option<Application>>
+    where
+        A: AsRef<str> + Debug,
+        F: FnMut(&mut Application),
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let mut app = match self.get_app(application.as_ref()).await? {
+                Some(app) => app,
+                None => return Ok(None),
+            };
+
+            mutator(&mut app);
+
+            match self.update_app(&app).await {
+                Ok(true) => return Ok(Some(app)),
+                Ok(false) => return Ok(None),
+                Err(ClientError::Conflict { .. }) if attempt < UPDATE_CONFLICT_ATTEMPTS => {
+                    log::debug!("Conflict updating application, retrying");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Update a device, using optimistic locking.
+    ///
+    /// This will read the current state of the device, apply the mutation, and write it back
+    /// using the resource version it was read with. If the device was modified in the meantime,
+    /// the process will be repeated, up to [`UPDATE_CONFLICT_ATTEMPTS`] times.
+    ///
+    /// If the application or device does not exist, `None` is returned. Otherwise the updated
+    /// device is returned.
+    #[instrument(skip(mutator))]
+    pub async fn update_device_with<A, D, F>(
+        &self,
+        application: A,
+        device: D,
+        mut mutator: F,
+    ) -> ClientResult<Option<Device>>
+    where
+        A: AsRef<str> + Debug,
+        D: AsRef<str> + Debug,
+        F: FnMut(&mut Device),
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let mut current = match self
+                .get_device(application.as_ref(), device.as_ref())
+                .await?
+            {
+                Some(device) => device,
+                None => return Ok(None),
+            };
+
+            mutator(&mut current);
+
+            match self.update_device(&current).await {
+                Ok(true) => return Ok(Some(current)),
+                Ok(false) => return Ok(None),
+                Err(ClientError::Conflict { .. }) if attempt < UPDATE_CONFLICT_ATTEMPTS => {
+                    log::debug!("Conflict updating device, retrying");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
     /// Create a new application.
     #[instrument]
     pub async fn create_app(&self, app: &Application) -> ClientResult<Option<()>> {
-        self.create(self.url(None, None)?, Some(app)).await
+        self.create("create_app", self.url(None, None)?, Some(app))
+            .await
     }
 
     #[instrument]
@@ -240,7 +594,7 @@ impl Client {
     where
         A: AsRef<str> + Debug,
     {
-        self.delete(self.url(Some(application.as_ref()), None)?)
+        self.delete("delete_app", self.url(Some(application.as_ref()), None)?)
             .await
     }
 
@@ -248,6 +602,7 @@ impl Client {
     #[instrument]
     pub async fn create_device(&self, device: &Device) -> ClientResult<Option<()>> {
         self.create(
+            "create_device",
             self.url(Some(device.metadata.application.as_str()), Some(""))?,
             Some(device),
         )
@@ -260,20 +615,428 @@ impl Client {
         A: AsRef<str> + Debug,
         D: AsRef<str> + Debug,
     {
-        self.delete(self.url(Some(application.as_ref()), Some(device.as_ref()))?)
-            .await
+        self.delete(
+            "delete_device",
+            self.url(Some(application.as_ref()), Some(device.as_ref()))?,
+        )
+        .await
     }
 }
 
+/// Create a stream of items, fetching pages using the `list` function.
+///
+/// Servers which don't support paging return all items for every page. This is detected when a
+/// page is not of the requested size, or when a later page starts with the same item (by name) as
+/// the first page, in which case the repeated page is dropped.
+fn paged<T, F, Fut>(
+    labels: Option<LabelSelector>,
+    page_size: usize,
+    name: fn(&T) -> &str,
+    list: F,
+) -> impl Stream<Item = ClientResult<T>>
+where
+    F: Fn(ListOptions) -> Fut,
+    Fut: std::future::Future<Output = ClientResult<Option<Vec<T>>>>,
+{
+    let page_size = page_size.max(1);
+
+    stream::try_unfold(
+        Some((0, None)),
+        move |state: Option<(usize, Option<String>)>| {
+            let page = state.as_ref().map(|(offset, _)| {
+                list(ListOptions {
+                    labels: labels.clone(),
+                    limit: Some(page_size),
+                    offset: Some(*offset),
+                })
+            });
+
+            async move {
+                let ((offset, first), page) = match (state, page) {
+                    (Some(state), Some(page)) => (state, page.await?.unwrap_or_default()),
+                    _ => return Ok(None),
+                };
+
+                // the server ignored the offset, and returned the first page again
+                if offset > 0 && first.is_some() && page.first().map(name) == first.as_deref() {
+                    return Ok(None);
+                }
+
+                // a short page is the last one, a longer page means that paging is not supported
+                let next = match page.len() == page_size {
+                    true => Some((
+                        offset + page_size,
+                        first.or_else(|| page.first().map(|item| name(item).to_string())),
+                    )),
+                    false => None,
+                };
+
+                Ok::<_, ClientError>(Some((stream::iter(page.into_iter().map(Ok)), next)))
+            }
+        },
+    )
+    .try_flatten()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::openid::NoTokenProvider;
+    use crate::{
+        core::{RetryPolicy, RetryTransport, Transport, TransportRequest, TransportResponse},
+        mock::MockServer,
+        openid::{Credentials, NoTokenProvider, TokenProvider},
+    };
+    use async_trait::async_trait;
+    use reqwest::{header::HeaderMap, Method, StatusCode};
+    use std::sync::Mutex;
+
+    /// A fake registry, holding a single device and rejecting updates with an outdated resource
+    /// version.
+    ///
+    /// For the first reads, the device gets modified concurrently right after reading it, so
+    /// that the following update conflicts.
+    #[derive(Debug)]
+    struct ConflictingRegistry {
+        device: Mutex<Device>,
+        conflicts: Mutex<usize>,
+    }
+
+    impl ConflictingRegistry {
+        fn modify(device: &mut Device) {
+            let version: u64 = device.metadata.resource_version.parse().unwrap();
+            device.metadata.resource_version = (version + 1).to_string();
+        }
+    }
+
+    #[async_trait]
+    impl Transport for ConflictingRegistry {
+        async fn execute(&self, request: TransportRequest) -> ClientResult<TransportResponse> {
+            let mut device = self.device.lock().unwrap();
+            let (status, body) = match request.method {
+                Method::GET => {
+                    let body = serde_json::to_vec(&*device)?;
+                    let mut conflicts = self.conflicts.lock().unwrap();
+                    if *conflicts > 0 {
+                        *conflicts -= 1;
+                        Self::modify(&mut device);
+                    }
+                    (StatusCode::OK, body)
+                }
+                Method::PUT => {
+                    let mut update: Device =
+                        serde_json::from_slice(request.body.as_deref().unwrap_or_default())?;
+                    if update.metadata.resource_version != device.metadata.resource_version {
+                        (StatusCode::CONFLICT, vec![])
+                    } else {
+                        Self::modify(&mut update);
+                        *device = update;
+                        (StatusCode::NO_CONTENT, vec![])
+                    }
+                }
+                _ => (StatusCode::METHOD_NOT_ALLOWED, vec![]),
+            };
+
+            Ok(TransportResponse {
+                status,
+                headers: HeaderMap::new(),
+                body,
+            })
+        }
+    }
+
+    fn conflicting_client(conflicts: usize) -> (Client, Arc<ConflictingRegistry>) {
+        let mut device = Device::new("app", "device");
+        device.metadata.resource_version = "1".into();
+        let registry = Arc::new(ConflictingRegistry {
+            device: Mutex::new(device),
+            conflicts: Mutex::new(conflicts),
+        });
+        let client = Client::new(
+            registry.clone(),
+            Url::parse("http://localhost").unwrap(),
+            NoTokenProvider,
+        );
+        (client, registry)
+    }
+
+    #[tokio::test]
+    async fn test_update_with_conflict() -> anyhow::Result<()> {
+        let (client, registry) = conflicting_client(2);
+
+        let mut calls = 0;
+        let device = client
+            .update_device_with("app", "device", |device| {
+                calls += 1;
+                device.metadata.labels.insert("foo".into(), "bar".into());
+            })
+            .await?
+            .unwrap();
+
+        assert_eq!(calls, 3);
+        assert_eq!(device.metadata.labels["foo"], "bar");
+        // written with the version of the third read, after two concurrent modifications
+        assert_eq!(device.metadata.resource_version, "3");
+
+        let stored = registry.device.lock().unwrap().clone();
+        assert_eq!(stored.metadata.labels["foo"], "bar");
+        assert_eq!(stored.metadata.resource_version, "4");
+
+        // writing the outdated version again must fail
+        assert!(matches!(
+            client.update_device(&device).await,
+            Err(ClientError::Conflict { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_with_conflict_exhausted() {
+        let (client, registry) = conflicting_client(UPDATE_CONFLICT_ATTEMPTS);
+
+        let result = client
+            .update_device_with("app", "device", |device| {
+                device.metadata.labels.insert("foo".into(), "bar".into());
+            })
+            .await;
+
+        assert!(matches!(result, Err(ClientError::Conflict { .. })));
+        assert!(registry.device.lock().unwrap().metadata.labels.is_empty());
+    }
+
+    /// A fake registry, serving a list of devices, optionally supporting paging.
+    #[derive(Debug)]
+    struct PagingRegistry {
+        devices: usize,
+        paging: bool,
+    }
+
+    #[async_trait]
+    impl Transport for PagingRegistry {
+        async fn execute(&self, request: TransportRequest) -> ClientResult<TransportResponse> {
+            let param = |name: &str| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(k, _)| k == name)
+                    .and_then(|(_, v)| v.parse::<usize>().ok())
+            };
+
+            let devices = (0..self.devices).map(|i| Device::new("app", format!("device-{}", i)));
+            let devices: Vec<_> = match self.paging {
+                true => devices
+                    .skip(param("offset").unwrap_or_default())
+                    .take(param("limit").unwrap_or(usize::MAX))
+                    .collect(),
+                false => devices.collect(),
+            };
+
+            Ok(TransportResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: serde_json::to_vec(&devices)?,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_devices_stream() -> anyhow::Result<()> {
+        for (devices, paging) in [(0, true), (10, true), (25, true), (10, false), (25, false)] {
+            let client = Client::new(
+                PagingRegistry { devices, paging },
+                Url::parse("http://localhost")?,
+                NoTokenProvider,
+            );
+
+            let names: Vec<_> = client
+                .list_devices_stream("app", None, 10)
+                .map_ok(|device| device.metadata.name)
+                .try_collect()
+                .await?;
+
+            assert_eq!(
+                names,
+                (0..devices)
+                    .map(|i| format!("device-{}", i))
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// A fake registry, knowing devices "0" to "9", answering slower for lower numbers.
+    #[derive(Debug, Default)]
+    struct SlowRegistry {
+        in_flight: Mutex<(usize, usize)>,
+    }
+
+    #[async_trait]
+    impl Transport for SlowRegistry {
+        async fn execute(&self, request: TransportRequest) -> ClientResult<TransportResponse> {
+            {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                in_flight.0 += 1;
+                in_flight.1 = in_flight.1.max(in_flight.0);
+            }
+
+            let name = request
+                .url
+                .path_segments()
+                .unwrap()
+                .next_back()
+                .unwrap()
+                .to_string();
+            let response = match name.parse::<u64>() {
+                Ok(n) if n < 10 => {
+                    tokio::time::sleep(std::time::Duration::from_millis(10 * (10 - n))).await;
+                    TransportResponse {
+                        status: StatusCode::OK,
+                        headers: HeaderMap::new(),
+                        body: serde_json::to_vec(&Device::new("app", name))?,
+                    }
+                }
+                _ => TransportResponse {
+                    status: StatusCode::NOT_FOUND,
+                    headers: HeaderMap::new(),
+                    body: vec![],
+                },
+            };
+
+            self.in_flight.lock().unwrap().0 -= 1;
+            Ok(response)
+        }
+    }
+
+    /// A token provider, taking some time to provide a token.
+    #[derive(Debug)]
+    struct SlowTokenProvider;
+
+    #[async_trait]
+    impl TokenProvider for SlowTokenProvider {
+        async fn provide_access_token(&self) -> ClientResult<Option<Credentials>> {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(Some(Credentials::Bearer("token".into())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_covers_token() -> anyhow::Result<()> {
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        let client = Client::new(server.clone(), server.api_url(), SlowTokenProvider);
+
+        assert!(client.get_app("app").await?.is_some());
+
+        let result = client
+            .with_options(RequestOptions::new().timeout(Duration::from_millis(50)))
+            .get_app("app")
+            .await;
+        assert!(matches!(result, Err(ClientError::Timeout)));
+
+        // the options only apply to the copy of the client
+        assert!(client.get_app("app").await?.is_some());
+
+        Ok(())
+    }
+
+    /// A transport, responding slowly with "service unavailable", and ignoring the timeout.
+    #[derive(Debug, Default)]
+    struct SlowUnavailable {
+        attempts: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl Transport for SlowUnavailable {
+        async fn execute(&self, _: TransportRequest) -> ClientResult<TransportResponse> {
+            *self.attempts.lock().unwrap() += 1;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(TransportResponse {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                headers: HeaderMap::new(),
+                body: vec![],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_covers_retries() -> anyhow::Result<()> {
+        let transport = Arc::new(SlowUnavailable::default());
+        let client = Client::new(
+            RetryTransport::new(
+                transport.clone(),
+                RetryPolicy {
+                    initial_backoff: Duration::from_millis(10),
+                    jitter: 0.0,
+                    ..Default::default()
+                },
+            ),
+            Url::parse("http://localhost")?,
+            NoTokenProvider,
+        )
+        .with_timeout(Duration::from_millis(150));
+
+        let start = std::time::Instant::now();
+        let result = client.get_app("app").await;
+
+        assert!(matches!(result, Err(ClientError::Timeout)));
+        assert!(start.elapsed() < Duration::from_millis(300));
+        assert_eq!(*transport.attempts.lock().unwrap(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_devices_concurrent() -> anyhow::Result<()> {
+        let registry = Arc::new(SlowRegistry::default());
+        let client = Client::new(
+            registry.clone(),
+            Url::parse("http://localhost")?,
+            NoTokenProvider,
+        )
+        .with_concurrency(3);
+
+        let lookup = client
+            .get_devices_with_missing("app", &["0", "foo", "1", "2", "bar", "3", "4"])
+            .await?;
+
+        assert_eq!(
+            lookup
+                .found
+                .iter()
+                .map(|device| device.metadata.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["0", "1", "2", "3", "4"]
+        );
+        assert_eq!(lookup.missing, vec!["foo", "bar"]);
+        let max_in_flight = registry.in_flight.lock().unwrap().1;
+        assert!(max_in_flight > 1 && max_in_flight <= 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_options() {
+        let options = ListOptions {
+            labels: Some(LabelSelector::from(vec!["foo"])),
+            limit: Some(10),
+            offset: Some(20),
+        };
+
+        assert_eq!(
+            options.to_query_parameters(),
+            vec![
+                ("labels".to_string(), "foo".to_string()),
+                ("limit".to_string(), "10".to_string()),
+                ("offset".to_string(), "20".to_string()),
+            ]
+        );
+    }
 
     #[test]
     fn test_url_list() -> anyhow::Result<()> {
         let client = Client::new(
-            Default::default(),
+            reqwest::Client::new(),
             Url::parse("http://localhost")?,
             NoTokenProvider,
         );
@@ -290,7 +1053,7 @@ mod test {
     #[test]
     fn test_url_app() -> anyhow::Result<()> {
         let client = Client::new(
-            Default::default(),
+            reqwest::Client::new(),
             Url::parse("http://localhost")?,
             NoTokenProvider,
         );
@@ -0,0 +1,416 @@
+use super::{Device, DeviceSpecGatewaySelector};
+use crate::Translator;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The graph of gateways of a device.
+///
+/// The graph starts with a device, and contains its gateways, the gateways of those gateways,
+/// and so on, as configured by the [`DeviceSpecGatewaySelector`] of each device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GatewayGraph {
+    root: String,
+    devices: BTreeMap<String, Device>,
+    gateways: BTreeMap<String, Vec<String>>,
+    depths: BTreeMap<String, usize>,
+    missing: BTreeSet<String>,
+    truncated: bool,
+    frontier: Vec<String>,
+}
+
+impl GatewayGraph {
+    /// Start a new graph, with the device as root.
+    pub(crate) fn new(device: Device) -> Self {
+        let root = device.metadata.name.clone();
+        let mut graph = Self {
+            root: root.clone(),
+            devices: Default::default(),
+            gateways: Default::default(),
+            depths: Default::default(),
+            missing: Default::default(),
+            truncated: false,
+            frontier: vec![root],
+        };
+        graph.insert(device, 0);
+        graph
+    }
+
+    /// Resolve the gateway graph of a device, using a list of known devices.
+    ///
+    /// Returns `None` if the device is not part of the list.
+    pub fn from_devices(device: &str, devices: &[Device], max_depth: usize) -> Option<Self> {
+        let devices: BTreeMap<_, _> = devices
+            .iter()
+            .map(|device| (device.metadata.name.as_str(), device))
+            .collect();
+
+        let mut graph = Self::new((*devices.get(device)?).clone());
+
+        while let Some(names) = graph.next_level(max_depth) {
+            let mut found = Vec::new();
+            let mut missing = Vec::new();
+            for name in names {
+                match devices.get(name.as_str()) {
+                    Some(device) => found.push((*device).clone()),
+                    None => missing.push(name),
+                }
+            }
+            graph.add_level(found, missing);
+        }
+
+        Some(graph)
+    }
+
+    /// Get the names of the devices which need to be resolved for the next level.
+    ///
+    /// Returns `None` if the resolution is complete, or `max_depth` was reached.
+    pub(crate) fn next_level(&mut self, max_depth: usize) -> Option<Vec<String>> {
+        let mut names = Vec::new();
+        for device in &self.frontier {
+            for gateway in self.gateways.get(device).into_iter().flatten() {
+                if !self.depths.contains_key(gateway)
+                    && !self.missing.contains(gateway)
+                    && !names.contains(gateway)
+                {
+                    names.push(gateway.clone());
+                }
+            }
+        }
+
+        if names.is_empty() {
+            return None;
+        }
+
+        let depth = self.depths[&self.frontier[0]];
+        if depth >= max_depth {
+            self.truncated = true;
+            return None;
+        }
+
+        Some(names)
+    }
+
+    /// Add the result of resolving the names returned by [`Self::next_level`].
+    pub(crate) fn add_level(&mut self, found: Vec<Device>, missing: Vec<String>) {
+        let depth = self.depths[&self.frontier[0]] + 1;
+
+        self.frontier = found.iter().map(|d| d.metadata.name.clone()).collect();
+        for device in found {
+            self.insert(device, depth);
+        }
+        self.missing.extend(missing);
+    }
+
+    fn insert(&mut self, device: Device, depth: usize) {
+        let name = device.metadata.name.clone();
+        let gateways = gateway_names(&device);
+
+        self.gateways.insert(name.clone(), gateways);
+        self.depths.insert(name.clone(), depth);
+        self.devices.insert(name, device);
+    }
+
+    /// The device the graph was resolved for.
+    pub fn device(&self) -> &Device {
+        &self.devices[&self.root]
+    }
+
+    /// Get a device of the graph by name.
+    pub fn get(&self, name: &str) -> Option<&Device> {
+        self.devices.get(name)
+    }
+
+    /// Get the names of the gateways, configured for a device of the graph.
+    ///
+    /// This includes gateways which could not be found.
+    pub fn gateways_of(&self, name: &str) -> &[String] {
+        self.gateways
+            .get(name)
+            .map(|gateways| gateways.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Get the distance of a device from the root device.
+    pub fn depth_of(&self, name: &str) -> Option<usize> {
+        self.depths.get(name).copied()
+    }
+
+    /// All gateways of the graph, not including the root device.
+    ///
+    /// This only contains gateways which could be found.
+    pub fn all_gateways(&self) -> impl Iterator<Item = &Device> {
+        self.devices
+            .iter()
+            .filter(|(name, _)| **name != self.root)
+            .map(|(_, device)| device)
+    }
+
+    /// Check if a device is a direct or indirect gateway of the root device.
+    ///
+    /// Only gateways which could be found are considered.
+    pub fn is_gateway(&self, name: &str) -> bool {
+        name != self.root && self.devices.contains_key(name)
+    }
+
+    /// Names of gateways which are referenced, but could not be found.
+    pub fn missing(&self) -> &BTreeSet<String> {
+        &self.missing
+    }
+
+    /// Check if the resolution stopped at the maximum depth, with gateways left unresolved.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Detect cycles in the graph.
+    ///
+    /// Each cycle is reported as the path of device names, starting and ending with the same
+    /// device. Not all cycles are enumerated: when cycles overlap, only some of them may be
+    /// reported. However, for each group of devices which are gateways of each other, directly
+    /// or indirectly, at least one cycle is reported.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+        let mut done = BTreeSet::new();
+        let mut path = Vec::new();
+        self.find_cycles(&self.root, &mut path, &mut done, &mut cycles);
+        cycles
+    }
+
+    /// Check if the graph contains cycles.
+    pub fn has_cycles(&self) -> bool {
+        !self.cycles().is_empty()
+    }
+
+    fn find_cycles<'a>(
+        &'a self,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if let Some(pos) = path.iter().position(|n| *n == name) {
+            let mut cycle: Vec<String> = path[pos..].iter().map(|n| n.to_string()).collect();
+            cycle.push(name.to_string());
+            cycles.push(cycle);
+            return;
+        }
+
+        if !self.devices.contains_key(name) || !done.insert(name) {
+            return;
+        }
+
+        path.push(name);
+        for gateway in self.gateways_of(name) {
+            self.find_cycles(gateway, path, done, cycles);
+        }
+        path.pop();
+    }
+}
+
+/// Build the inverse gateway index of a list of devices.
+///
+/// The index maps the name of each gateway to the names of the devices, which list it in their
+/// [`DeviceSpecGatewaySelector`]. Gateways which are referenced, but not part of the list, are
+/// included as well.
+pub fn gateway_index(devices: &[Device]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut index = BTreeMap::<_, BTreeSet<_>>::new();
+
+    for device in devices {
+        for gateway in gateway_names(device) {
+            index
+                .entry(gateway)
+                .or_default()
+                .insert(device.metadata.name.clone());
+        }
+    }
+
+    index
+}
+
+/// Get the names of the gateways configured for a device.
+fn gateway_names(device: &Device) -> Vec<String> {
+    device
+        .section::<DeviceSpecGatewaySelector>()
+        .and_then(|s| s.ok())
+        .map(|s| s.match_names)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "reqwest")]
+mod client {
+    use super::*;
+    use crate::{error::ClientError, registry::v1::Client};
+    use std::fmt::Debug;
+    use tracing::instrument;
+
+    impl Client {
+        /// Get a device by name, recursively resolving its gateways.
+        ///
+        /// Gateways are resolved up to `max_depth` levels, a depth of `1` only resolves the
+        /// direct gateways of the device. Each device is only fetched once, so that cycles in
+        /// the gateway configuration don't cause an endless resolution.
+        ///
+        /// If the device does not exist, `None` is returned.
+        #[instrument]
+        pub async fn resolve_gateway_graph<A, D>(
+            &self,
+            application: A,
+            device: D,
+            max_depth: usize,
+        ) -> Result<Option<GatewayGraph>, ClientError>
+        where
+            A: AsRef<str> + Debug,
+            D: AsRef<str> + Debug,
+        {
+            let device = match self.get_device(application.as_ref(), device).await? {
+                Some(device) => device,
+                None => return Ok(None),
+            };
+
+            let mut graph = GatewayGraph::new(device);
+
+            while let Some(names) = graph.next_level(max_depth) {
+                let lookup = self
+                    .get_devices_with_missing(application.as_ref(), &names)
+                    .await?;
+                graph.add_level(lookup.found, lookup.missing);
+            }
+
+            Ok(Some(graph))
+        }
+
+        /// Find all devices of an application, which list a gateway in their
+        /// [`DeviceSpecGatewaySelector`].
+        ///
+        /// This does not check if the gateway itself exists. As the registry does not offer a
+        /// server side filter for this, all devices of the application get listed.
+        ///
+        /// If the application does not exist, `None` is returned.
+        #[instrument]
+        pub async fn devices_using_gateway<A, G>(
+            &self,
+            application: A,
+            gateway: G,
+        ) -> Result<Option<Vec<Device>>, ClientError>
+        where
+            A: AsRef<str> + Debug,
+            G: AsRef<str> + Debug,
+        {
+            let gateway = gateway.as_ref();
+
+            Ok(self.list_devices(application, None).await?.map(|devices| {
+                devices
+                    .into_iter()
+                    .filter(|device| gateway_names(device).iter().any(|gw| gw == gateway))
+                    .collect()
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn device(name: &str, gateways: &[&str]) -> Device {
+        let mut device = Device::new("app", name);
+        if !gateways.is_empty() {
+            device
+                .set_section(DeviceSpecGatewaySelector {
+                    match_names: gateways.iter().map(|s| s.to_string()).collect(),
+                })
+                .unwrap();
+        }
+        device
+    }
+
+    fn devices() -> Vec<Device> {
+        vec![
+            device("device", &["gw1", "gw2"]),
+            device("gw1", &["gw3"]),
+            device("gw2", &["gw3", "gw4"]),
+            device("gw3", &["gw1", "gw5"]),
+            device("gw5", &[]),
+        ]
+    }
+
+    #[test]
+    fn test_graph() {
+        let graph = GatewayGraph::from_devices("device", &devices(), 10).unwrap();
+
+        assert_eq!(graph.device().metadata.name, "device");
+        assert_eq!(
+            graph
+                .all_gateways()
+                .map(|d| d.metadata.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["gw1", "gw2", "gw3", "gw5"]
+        );
+        assert_eq!(graph.gateways_of("gw2"), &["gw3", "gw4"]);
+        assert_eq!(graph.depth_of("gw3"), Some(2));
+        assert_eq!(graph.depth_of("gw5"), Some(3));
+        assert!(graph.is_gateway("gw5"));
+        assert!(!graph.is_gateway("gw4"));
+        assert!(!graph.is_gateway("device"));
+        assert_eq!(graph.missing().iter().collect::<Vec<_>>(), vec!["gw4"]);
+        assert!(!graph.is_truncated());
+        assert_eq!(graph.cycles(), vec![vec!["gw1", "gw3", "gw1"]]);
+    }
+
+    #[test]
+    fn test_graph_max_depth() {
+        let graph = GatewayGraph::from_devices("device", &devices(), 1).unwrap();
+
+        assert!(graph.is_gateway("gw1"));
+        assert!(graph.is_gateway("gw2"));
+        assert!(!graph.is_gateway("gw3"));
+        assert!(graph.is_truncated());
+        assert!(!graph.has_cycles());
+
+        let graph = GatewayGraph::from_devices("device", &devices(), 0).unwrap();
+        assert_eq!(graph.all_gateways().count(), 0);
+        assert!(graph.is_truncated());
+    }
+
+    #[test]
+    fn test_gateway_index() {
+        let index = gateway_index(&devices());
+
+        let using = |gateway: &str| -> Vec<&str> {
+            index
+                .get(gateway)
+                .into_iter()
+                .flatten()
+                .map(|s| s.as_str())
+                .collect()
+        };
+
+        assert_eq!(using("gw1"), vec!["device", "gw3"]);
+        assert_eq!(using("gw3"), vec!["gw1", "gw2"]);
+        assert_eq!(using("gw4"), vec!["gw2"]);
+        assert_eq!(using("device"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_graph_self_reference() {
+        let graph =
+            GatewayGraph::from_devices("device", &[device("device", &["device"])], 10).unwrap();
+
+        assert_eq!(graph.all_gateways().count(), 0);
+        assert_eq!(graph.cycles(), vec![vec!["device", "device"]]);
+    }
+
+    #[test]
+    fn test_graph_overlapping_cycles() {
+        let devices = vec![
+            device("device", &["a"]),
+            device("a", &["b", "c"]),
+            device("b", &["c"]),
+            device("c", &["a"]),
+        ];
+        let graph = GatewayGraph::from_devices("device", &devices, 10).unwrap();
+
+        // the cycle "a, c, a" overlaps, and is not reported
+        assert_eq!(graph.cycles(), vec![vec!["a", "b", "c", "a"]]);
+        assert!(graph.has_cycles());
+    }
+}
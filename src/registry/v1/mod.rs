@@ -1,7 +1,23 @@
+mod alias;
+mod batch;
+#[cfg(all(any(test, feature = "blocking"), not(target_arch = "wasm32")))]
+pub mod blocking;
+mod bundle;
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+mod cache;
 #[cfg(feature = "reqwest")]
 mod client;
 mod data;
+mod gateway;
+mod watch;
 
+pub use alias::*;
+pub use batch::*;
+pub use bundle::*;
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub use cache::*;
 #[cfg(feature = "reqwest")]
 pub use client::*;
 pub use data::*;
+pub use gateway::*;
+pub use watch::*;
@@ -0,0 +1,423 @@
+use crate::meta::v1::CommonMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// An event, reporting the change of a resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WatchEvent<T> {
+    /// The resource was added.
+    Added(T),
+    /// The resource was modified.
+    Modified(T),
+    /// The resource was deleted. Contains the last known state of the resource.
+    Deleted(T),
+}
+
+impl<T> WatchEvent<T> {
+    /// Get the resource of the event.
+    pub fn resource(&self) -> &T {
+        match self {
+            Self::Added(resource) | Self::Modified(resource) | Self::Deleted(resource) => resource,
+        }
+    }
+
+    /// Convert into the resource of the event.
+    pub fn into_resource(self) -> T {
+        match self {
+            Self::Added(resource) | Self::Modified(resource) | Self::Deleted(resource) => resource,
+        }
+    }
+}
+
+/// Tracks the known state of a list of resources, creating events from successive listings.
+///
+/// Resources are identified by their name, and considered modified when their resource version
+/// changed. As the state is kept between listings, a failed listing (e.g. due to a disconnect)
+/// does not cause any events to be lost or replayed. The next successful listing will report
+/// all changes since the last successful one.
+///
+/// The state can be serialized, and kept up to date using [`WatchState::observe`]. Resuming a
+/// watch with it only reports the changes which happened in the meantime.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WatchState<T> {
+    known: BTreeMap<String, T>,
+}
+
+impl<T> Default for WatchState<T> {
+    fn default() -> Self {
+        Self {
+            known: Default::default(),
+        }
+    }
+}
+
+impl<T> WatchState<T>
+where
+    T: AsRef<dyn CommonMetadata> + PartialEq,
+{
+    /// Create a state from a list of known resources.
+    pub fn from_resources(resources: Vec<T>) -> Self {
+        Self {
+            known: resources
+                .into_iter()
+                .map(|resource| (resource.as_ref().name().clone(), resource))
+                .collect(),
+        }
+    }
+
+    /// The known resources.
+    pub fn resources(&self) -> impl Iterator<Item = &T> {
+        self.known.values()
+    }
+
+    /// Update the state with an event, e.g. one which was processed by the caller.
+    pub fn observe(&mut self, event: &WatchEvent<T>)
+    where
+        T: Clone,
+    {
+        match event {
+            WatchEvent::Added(resource) | WatchEvent::Modified(resource) => {
+                self.known
+                    .insert(resource.as_ref().name().clone(), resource.clone());
+            }
+            WatchEvent::Deleted(resource) => {
+                self.known.remove(resource.as_ref().name());
+            }
+        }
+    }
+
+    /// Apply a new listing, returning the events required to get from the previous state to the
+    /// new one.
+    pub fn apply(&mut self, current: Vec<T>) -> Vec<WatchEvent<T>>
+    where
+        T: Clone,
+    {
+        let mut events = Vec::new();
+        let mut next = BTreeMap::new();
+
+        for resource in current {
+            let name = resource.as_ref().name().clone();
+            match self.known.remove(&name) {
+                None => events.push(WatchEvent::Added(resource.clone())),
+                Some(known) if Self::is_modified(&known, &resource) => {
+                    events.push(WatchEvent::Modified(resource.clone()))
+                }
+                Some(_) => {}
+            }
+            next.insert(name, resource);
+        }
+
+        let deleted = std::mem::replace(&mut self.known, next);
+        events.extend(deleted.into_values().map(WatchEvent::Deleted));
+
+        events
+    }
+
+    fn is_modified(known: &T, current: &T) -> bool {
+        let known_version = known.as_ref().resource_version();
+        let current_version = current.as_ref().resource_version();
+
+        if known_version.is_empty() || current_version.is_empty() {
+            // no resource version, compare content
+            known != current
+        } else {
+            known_version != current_version
+        }
+    }
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+mod client {
+    use super::*;
+    use crate::{
+        error::ClientError,
+        registry::v1::{labels::LabelSelector, Application, Client, Device},
+    };
+    use futures::{stream, Stream};
+    use std::{collections::VecDeque, fmt::Debug, time::Duration};
+
+    struct Poller<T> {
+        state: WatchState<T>,
+        pending: VecDeque<WatchEvent<T>>,
+        initial: bool,
+    }
+
+    /// Create a stream of events, by calling `list` once per interval.
+    ///
+    /// Every call transfers the full listing, whether or not anything changed. Only resources
+    /// with a changed resource version get reported.
+    fn poll<T, F, Fut>(
+        state: WatchState<T>,
+        interval: Duration,
+        list: F,
+    ) -> impl Stream<Item = Result<WatchEvent<T>, ClientError>>
+    where
+        T: AsRef<dyn CommonMetadata> + PartialEq + Clone,
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<Option<Vec<T>>, ClientError>>,
+    {
+        let poller = Poller {
+            state,
+            pending: VecDeque::new(),
+            initial: true,
+        };
+
+        stream::unfold((poller, list), move |(mut poller, list)| async move {
+            loop {
+                if let Some(event) = poller.pending.pop_front() {
+                    return Some((Ok(event), (poller, list)));
+                }
+
+                if !poller.initial {
+                    tokio::time::sleep(interval).await;
+                }
+                poller.initial = false;
+
+                match list().await {
+                    Ok(current) => {
+                        let events = poller.state.apply(current.unwrap_or_default());
+                        poller.pending.extend(events);
+                    }
+                    Err(err) => return Some((Err(err), (poller, list))),
+                }
+            }
+        })
+    }
+
+    impl Client {
+        /// Watch applications for changes.
+        ///
+        /// The registry API does not provide a server side watch operation. So this is implemented
+        /// by periodically listing the applications, and comparing the result with the previous
+        /// listing. The first listing will report all existing applications as added.
+        ///
+        /// Each interval fetches all matching applications, even if none of them changed. The
+        /// interval should be chosen with the number of applications in mind, using a label
+        /// selector helps to reduce the load.
+        ///
+        /// Errors are reported as part of the stream, but don't end it. The next listing will
+        /// report all changes since the last successful listing.
+        pub fn watch_apps(
+            &self,
+            labels: Option<LabelSelector>,
+            interval: Duration,
+        ) -> impl Stream<Item = Result<WatchEvent<Application>, ClientError>> {
+            self.watch_apps_from(WatchState::default(), labels, interval)
+        }
+
+        /// Resume watching applications, starting with a known state.
+        ///
+        /// The first listing only reports the changes compared to the state, including
+        /// applications which were deleted in the meantime. See [`Self::watch_apps`] for details.
+        pub fn watch_apps_from(
+            &self,
+            state: WatchState<Application>,
+            labels: Option<LabelSelector>,
+            interval: Duration,
+        ) -> impl Stream<Item = Result<WatchEvent<Application>, ClientError>> {
+            let client = self.clone();
+            poll(state, interval, move || {
+                let client = client.clone();
+                let labels = labels.clone();
+                async move { client.list_apps(labels).await }
+            })
+        }
+
+        /// Watch the devices of an application for changes.
+        ///
+        /// The registry API does not provide a server side watch operation. So this is implemented
+        /// by periodically listing the devices, and comparing the result with the previous
+        /// listing. The first listing will report all existing devices as added.
+        ///
+        /// Each interval fetches all matching devices of the application, even if none of them
+        /// changed. For applications with many devices, a longer interval or a label selector
+        /// keeps the load on the registry down.
+        ///
+        /// Errors are reported as part of the stream, but don't end it. The next listing will
+        /// report all changes since the last successful listing.
+        ///
+        /// Deleting the application deletes its devices, so if the application cannot be found,
+        /// all known devices are reported as deleted. The watch continues, and reports devices as
+        /// added when the application gets re-created.
+        pub fn watch_devices<A>(
+            &self,
+            application: A,
+            labels: Option<LabelSelector>,
+            interval: Duration,
+        ) -> impl Stream<Item = Result<WatchEvent<Device>, ClientError>>
+        where
+            A: AsRef<str> + Debug,
+        {
+            self.watch_devices_from(WatchState::default(), application, labels, interval)
+        }
+
+        /// Resume watching the devices of an application, starting with a known state.
+        ///
+        /// The first listing only reports the changes compared to the state, including devices
+        /// which were deleted in the meantime. See [`Self::watch_devices`] for details.
+        pub fn watch_devices_from<A>(
+            &self,
+            state: WatchState<Device>,
+            application: A,
+            labels: Option<LabelSelector>,
+            interval: Duration,
+        ) -> impl Stream<Item = Result<WatchEvent<Device>, ClientError>>
+        where
+            A: AsRef<str> + Debug,
+        {
+            let client = self.clone();
+            let application = application.as_ref().to_string();
+            poll(state, interval, move || {
+                let client = client.clone();
+                let application = application.clone();
+                let labels = labels.clone();
+                async move { client.list_devices(application, labels).await }
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registry::v1::Device;
+
+    fn device(name: &str, version: &str) -> Device {
+        let mut device = Device::new("app", name);
+        device.metadata.resource_version = version.into();
+        device.metadata.creation_timestamp = Default::default();
+        device
+    }
+
+    #[test]
+    fn test_watch_state() {
+        let mut state = WatchState::default();
+
+        let events = state.apply(vec![device("a", "1"), device("b", "1")]);
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Added(device("a", "1")),
+                WatchEvent::Added(device("b", "1"))
+            ]
+        );
+
+        let events = state.apply(vec![device("a", "1"), device("b", "1")]);
+        assert_eq!(events, vec![]);
+
+        let events = state.apply(vec![device("b", "2"), device("c", "1")]);
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Modified(device("b", "2")),
+                WatchEvent::Added(device("c", "1")),
+                WatchEvent::Deleted(device("a", "1")),
+            ]
+        );
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn test_watch_devices() -> anyhow::Result<()> {
+        use crate::{
+            mock::MockServer,
+            openid::NoTokenProvider,
+            registry::v1::{Application, Client},
+        };
+        use futures::StreamExt;
+        use serde_json::json;
+        use std::time::Duration;
+
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        server.add_device(Device::new("app", "a"));
+        server.add_device(Device::new("app", "b"));
+
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider);
+        let events = client.watch_devices("app", None, Duration::from_millis(10));
+        futures::pin_mut!(events);
+
+        async fn next<S: futures::Stream + Unpin>(events: &mut S) -> S::Item {
+            tokio::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .expect("event")
+                .expect("stream")
+        }
+
+        assert!(matches!(next(&mut events).await?, WatchEvent::Added(d) if d.metadata.name == "a"));
+        assert!(matches!(next(&mut events).await?, WatchEvent::Added(d) if d.metadata.name == "b"));
+
+        // unchanged devices are not reported again, only the modified one
+
+        let mut device = server.device("app", "b").unwrap();
+        device.spec.insert("foo".into(), json!("bar"));
+        assert!(client.update_device(&device).await?);
+
+        match next(&mut events).await? {
+            WatchEvent::Modified(device) => {
+                assert_eq!(device.metadata.name, "b");
+                assert_eq!(device.spec["foo"], json!("bar"));
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn test_watch_devices_resume() -> anyhow::Result<()> {
+        use crate::{
+            mock::MockServer,
+            openid::NoTokenProvider,
+            registry::v1::{Application, Client},
+        };
+        use futures::StreamExt;
+        use serde_json::json;
+        use std::time::Duration;
+
+        let server = MockServer::new();
+        server.add_app(Application::new("app"));
+        for name in ["a", "b", "c"] {
+            server.add_device(Device::new("app", name));
+        }
+
+        let client = Client::new(server.clone(), server.api_url(), NoTokenProvider);
+
+        // keep the state of the processed events, and persist it
+
+        let mut state = WatchState::default();
+        {
+            let events = client.watch_devices("app", None, Duration::from_millis(10));
+            futures::pin_mut!(events);
+            for _ in 0..3 {
+                state.observe(&events.next().await.unwrap()?);
+            }
+        }
+        let state: WatchState<Device> = serde_json::from_value(serde_json::to_value(&state)?)?;
+
+        // change while being disconnected
+
+        let mut device = server.device("app", "b").unwrap();
+        device.spec.insert("foo".into(), json!("bar"));
+        assert!(client.update_device(&device).await?);
+        assert!(client.delete_device("app", "c").await?);
+        client.create_device(&Device::new("app", "d")).await?;
+
+        let events = client.watch_devices_from(state, "app", None, Duration::from_millis(10));
+        futures::pin_mut!(events);
+        let mut changes = Vec::new();
+        for _ in 0..3 {
+            changes.push(match events.next().await.unwrap()? {
+                WatchEvent::Added(d) => format!("added {}", d.metadata.name),
+                WatchEvent::Modified(d) => format!("modified {}", d.metadata.name),
+                WatchEvent::Deleted(d) => format!("deleted {}", d.metadata.name),
+            });
+        }
+
+        assert_eq!(changes, vec!["modified b", "added d", "deleted c"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! A blocking variant of the tokens client.
+
+use super::{AccessToken, CreatedAccessToken};
+use crate::{
+    core::{BlockingRuntime, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+};
+use std::{fmt::Debug, time::Duration};
+use url::Url;
+
+type ClientResult<T> = Result<T, ClientError>;
+
+/// A blocking access token client.
+///
+/// This wraps the async [`Client`](super::Client), and runs its operations on a
+/// [`BlockingRuntime`]. The operations must not be called from within an async context.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client,
+    runtime: BlockingRuntime,
+}
+
+impl Client {
+    /// Create a new client instance, using its own runtime.
+    pub fn new(
+        transport: impl Transport + 'static,
+        api_url: Url,
+        token_provider: impl TokenProvider + 'static,
+    ) -> ClientResult<Self> {
+        Self::from_async(super::Client::new(transport, api_url, token_provider))
+    }
+
+    /// Wrap an async client, using its own runtime.
+    pub fn from_async(client: super::Client) -> ClientResult<Self> {
+        Ok(Self::with_runtime(client, BlockingRuntime::new()?))
+    }
+
+    /// Wrap an async client, using an existing runtime.
+    pub fn with_runtime(client: super::Client, runtime: BlockingRuntime) -> Self {
+        Self {
+            inner: client,
+            runtime,
+        }
+    }
+
+    /// The wrapped async client.
+    pub fn inner(&self) -> &super::Client {
+        &self.inner
+    }
+
+    /// Set a timeout for requests made by this client.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.with_timeout(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        Self {
+            inner: self.inner.with_options(options),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Blocking variant of [`super::Client::get_tokens`].
+    pub fn get_tokens(&self) -> ClientResult<Option<Vec<AccessToken>>> {
+        self.runtime.block_on(self.inner.get_tokens())
+    }
+
+    /// Blocking variant of [`super::Client::create_token`].
+    pub fn create_token<D>(
+        &self,
+        description: Option<D>,
+    ) -> ClientResult<Option<CreatedAccessToken>>
+    where
+        D: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.create_token(description))
+    }
+
+    /// Blocking variant of [`super::Client::delete_token`].
+    pub fn delete_token<P>(&self, prefix: P) -> ClientResult<bool>
+    where
+        P: AsRef<str> + Debug,
+    {
+        self.runtime.block_on(self.inner.delete_token(prefix))
+    }
+}
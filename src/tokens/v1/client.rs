@@ -1,43 +1,68 @@
 use super::data::*;
-use crate::core::CoreClient;
+use crate::core::{CoreClient, RequestOptions, Transport};
 use crate::error::ClientError;
 use crate::openid::TokenProvider;
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 use tracing::instrument;
 use url::Url;
 
 /// A client for the token management API, backed by reqwest.
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     api_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
 }
 
 type ClientResult<T> = Result<T, ClientError>;
 
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "tokens"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new client instance.
     pub fn new(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         api_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             api_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+        }
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
         }
+        client
     }
 
     fn url(&self, prefix: Option<&str>) -> ClientResult<Url> {
@@ -65,7 +90,7 @@ impl Client {
     /// The result contains the prefix and creation date for each active token.
     #[instrument]
     pub async fn get_tokens(&self) -> ClientResult<Option<Vec<AccessToken>>> {
-        self.read(self.url(Some(""))?).await
+        self.read("get_tokens", self.url(Some(""))?).await
     }
 
     /// Create a new access token for this user.
@@ -84,7 +109,7 @@ impl Client {
         let param =
             description.map(move |d| vec![("description".to_string(), d.as_ref().to_string())]);
 
-        self.create_with_query_parameters(url, None::<()>, param)
+        self.create_with_query_parameters("create_token", url, None::<()>, param)
             .await
     }
 
@@ -94,6 +119,7 @@ impl Client {
     where
         P: AsRef<str> + Debug,
     {
-        self.delete(self.url(Some(prefix.as_ref()))?).await
+        self.delete("delete_token", self.url(Some(prefix.as_ref()))?)
+            .await
     }
 }
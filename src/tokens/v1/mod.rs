@@ -1,3 +1,5 @@
+#[cfg(all(any(test, feature = "blocking"), not(target_arch = "wasm32")))]
+pub mod blocking;
 #[cfg(feature = "reqwest")]
 mod client;
 mod data;
@@ -1,6 +1,10 @@
 use super::{authn, authz};
-use crate::{core::CoreClient, error::ClientError, openid::TokenProvider};
-use std::sync::Arc;
+use crate::{
+    core::{CoreClient, RequestOptions, Transport},
+    error::ClientError,
+    openid::TokenProvider,
+};
+use std::{sync::Arc, time::Duration};
 use tracing::instrument;
 use url::Url;
 
@@ -9,53 +13,83 @@ use crate::metrics::PassFailErrorExt;
 
 #[cfg(feature = "telemetry")]
 lazy_static::lazy_static! {
-    pub static ref AUTHENTICATION: prometheus::IntGaugeVec = prometheus::register_int_gauge_vec!(
-        "drogue_client_user_authentication_access_token",
-        "User access token authentication operations",
-        &["outcome"]
-    )
-    .unwrap();
-    pub static ref AUTHORIZATION: prometheus::IntGaugeVec = prometheus::register_int_gauge_vec!(
-        "drogue_client_user_authorization",
-        "User access authorization",
-        &["outcome"]
-    )
-    .unwrap();
+    pub static ref AUTHENTICATION: prometheus::IntGaugeVec = crate::metrics::register(
+        prometheus::IntGaugeVec::new(
+            prometheus::Opts::new(
+                "drogue_client_user_authentication_access_token",
+                "User access token authentication operations"
+            ),
+            &["outcome"]
+        )
+        .unwrap()
+    );
+    pub static ref AUTHORIZATION: prometheus::IntGaugeVec = crate::metrics::register(
+        prometheus::IntGaugeVec::new(
+            prometheus::Opts::new("drogue_client_user_authorization", "User access authorization"),
+            &["outcome"]
+        )
+        .unwrap()
+    );
 }
 
 /// A client for authorizing user requests.
 #[derive(Clone, Debug)]
 pub struct Client {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     authn_url: Url,
     authz_url: Url,
     token_provider: Arc<dyn TokenProvider>,
+    timeout: Option<Duration>,
 }
 
 impl CoreClient for Client {
-    fn client(&self) -> &reqwest::Client {
-        &self.client
+    fn service(&self) -> &'static str {
+        "user"
+    }
+
+    fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
     }
 
     fn token_provider(&self) -> &dyn TokenProvider {
         self.token_provider.as_ref()
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
 }
 
 impl Client {
     /// Create a new client instance.
     pub fn new(
-        client: reqwest::Client,
+        transport: impl Transport + 'static,
         authn_url: Url,
         authz_url: Url,
         token_provider: impl TokenProvider + 'static,
     ) -> Self {
         Self {
-            client,
+            transport: Arc::new(transport),
             authn_url,
             authz_url,
             token_provider: Arc::new(token_provider),
+            timeout: None,
+        }
+    }
+
+    /// Set a timeout for the calls of this client, see [`RequestOptions::timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Create a copy of this client, applying the [`RequestOptions`] to its calls.
+    pub fn with_options(&self, options: RequestOptions) -> Self {
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.timeout = Some(timeout);
         }
+        client
     }
 
     #[allow(clippy::let_and_return)]
@@ -65,7 +99,11 @@ impl Client {
         request: authn::AuthenticationRequest,
     ) -> Result<authn::AuthenticationResponse, ClientError> {
         let resp = self
-            .create(self.authn_url.clone(), Some(&request))
+            .create(
+                "authenticate_access_token",
+                self.authn_url.clone(),
+                Some(&request),
+            )
             .await?
             .ok_or_else(|| ClientError::UnexpectedResponse("Missing response payload".to_string()));
 
@@ -82,7 +120,7 @@ impl Client {
         request: authz::AuthorizationRequest,
     ) -> Result<authz::AuthorizationResponse, ClientError> {
         let resp = self
-            .create(self.authz_url.clone(), Some(&request))
+            .create("authorize", self.authz_url.clone(), Some(&request))
             .await?
             .ok_or_else(|| ClientError::UnexpectedResponse("Missing response payload".to_string()));
 
@@ -1,5 +1,7 @@
 //! Version 1
 
+#[cfg(all(any(test, feature = "blocking"), not(target_arch = "wasm32")))]
+pub mod blocking;
 #[cfg(feature = "reqwest")]
 mod client;
 mod data;